@@ -0,0 +1,100 @@
+// Flipbook frame lookup, see `Flipbook`. The uniforms are set by `Flipbook::apply`, which
+// binds the frames to unit 13 and the motion vectors to unit 14. The including shader
+// declares those samplers, `sampler2D` for atlases and `sampler2DArray` for arrays, and
+// passes them to `flipbook_sample`.
+
+#define FLIPBOOK_NONE 0u
+#define FLIPBOOK_BLEND 1u
+#define FLIPBOOK_MOTION_VECTORS 2u
+
+uniform uint flipbook_columns;
+uniform uint flipbook_rows;
+uniform uint flipbook_frame_count;
+uniform float flipbook_fps;
+uniform uint flipbook_looping;
+uniform uint flipbook_interpolation;
+uniform float flipbook_motion_strength;
+
+struct FlipbookFrame {
+    uint current;
+    uint next;
+    // Weight of `next`, 0 without interpolation.
+    float blend;
+};
+
+// Frames shown `time` seconds into the animation.
+FlipbookFrame flipbook_frame(float time) {
+    float position = max(time * flipbook_fps, 0.0);
+    uint current = uint(position);
+    float blend = fract(position);
+    uint next;
+
+    if (flipbook_looping != 0u) {
+        current %= flipbook_frame_count;
+        next = (current + 1u) % flipbook_frame_count;
+    } else if (current + 1u >= flipbook_frame_count) {
+        current = flipbook_frame_count - 1u;
+        next = current;
+        blend = 0.0;
+    } else {
+        next = current + 1u;
+    }
+
+    if (flipbook_interpolation == FLIPBOOK_NONE) {
+        blend = 0.0;
+    }
+    return FlipbookFrame(current, next, blend);
+}
+
+// Texture coordinates of `uv` within `frame` of an atlas, kept inside the frame.
+vec2 flipbook_atlas_uv(uint frame, vec2 uv) {
+    vec2 cell = vec2(frame % flipbook_columns, frame / flipbook_columns);
+    return (cell + clamp(uv, 0.0, 1.0)) / vec2(flipbook_columns, flipbook_rows);
+}
+
+// Motion of the texel at `uv` by the frame after `frame`, in frame coordinates.
+vec2 flipbook_motion(sampler2D motion, uint frame, vec2 uv) {
+    return (texture(motion, flipbook_atlas_uv(frame, uv)).rg * 2.0 - 1.0) * flipbook_motion_strength;
+}
+
+vec2 flipbook_motion(sampler2DArray motion, uint frame, vec2 uv) {
+    return (texture(motion, vec3(uv, float(frame))).rg * 2.0 - 1.0) * flipbook_motion_strength;
+}
+
+// Color of the flipbook at `uv` of the frame shown at `time`, from an atlas. `motion` is
+// only sampled with motion vector interpolation.
+vec4 flipbook_sample(sampler2D frames, sampler2D motion, vec2 uv, float time) {
+    FlipbookFrame frame = flipbook_frame(time);
+    vec2 current_uv = uv;
+    vec2 next_uv = uv;
+    if (flipbook_interpolation == FLIPBOOK_MOTION_VECTORS) {
+        // Push the current frame forward and pull the next one back to meet in between.
+        current_uv -= flipbook_motion(motion, frame.current, uv) * frame.blend;
+        next_uv += flipbook_motion(motion, frame.next, uv) * (1.0 - frame.blend);
+    }
+
+    vec4 current = texture(frames, flipbook_atlas_uv(frame.current, current_uv));
+    if (frame.blend == 0.0) {
+        return current;
+    }
+    vec4 next = texture(frames, flipbook_atlas_uv(frame.next, next_uv));
+    return mix(current, next, frame.blend);
+}
+
+// Color of the flipbook at `uv` of the frame shown at `time`, from a texture array.
+vec4 flipbook_sample(sampler2DArray frames, sampler2DArray motion, vec2 uv, float time) {
+    FlipbookFrame frame = flipbook_frame(time);
+    vec2 current_uv = uv;
+    vec2 next_uv = uv;
+    if (flipbook_interpolation == FLIPBOOK_MOTION_VECTORS) {
+        current_uv -= flipbook_motion(motion, frame.current, uv) * frame.blend;
+        next_uv += flipbook_motion(motion, frame.next, uv) * (1.0 - frame.blend);
+    }
+
+    vec4 current = texture(frames, vec3(current_uv, float(frame.current)));
+    if (frame.blend == 0.0) {
+        return current;
+    }
+    vec4 next = texture(frames, vec3(next_uv, float(frame.next)));
+    return mix(current, next, frame.blend);
+}
@@ -0,0 +1,285 @@
+// Tileable noise matching `noise.rs`, for the generator compute shaders or for sampling
+// noise directly.
+//
+// Positions are in tile coordinates, where the pattern repeats every unit. Lattice cells are
+// wrapped with `noise_wrap`, which expects coordinates above -16 periods.
+
+#define NOISE_PERLIN 0u
+#define NOISE_SIMPLEX 1u
+#define NOISE_WORLEY 2u
+
+#define NOISE_VALUE 0u
+#define NOISE_CURL 1u
+
+struct NoiseParams {
+    uint kind;
+    uint seed;
+    // Number of lattice cells across the tile for the first octave.
+    uint period;
+    uint octaves;
+    float gain;
+};
+
+const float NOISE_CURL_EPSILON = 1e-3;
+const uint NOISE_CURL_SEEDS[3] = uint[3](0u, 0x5f356495u, 0x9e3779b9u);
+
+const float NOISE_SIMPLEX_2D_SCALE = 10.9;
+const float NOISE_SIMPLEX_3D_SCALE = 28.0;
+
+const float NOISE_D = 0.70710678;
+const vec2 NOISE_GRADIENTS_2D[8] = vec2[8](
+    vec2(1.0, 0.0), vec2(-1.0, 0.0), vec2(0.0, 1.0), vec2(0.0, -1.0),
+    vec2(NOISE_D, NOISE_D), vec2(-NOISE_D, NOISE_D), vec2(NOISE_D, -NOISE_D), vec2(-NOISE_D, -NOISE_D)
+);
+const vec3 NOISE_GRADIENTS_3D[12] = vec3[12](
+    vec3(1.0, 1.0, 0.0), vec3(-1.0, 1.0, 0.0), vec3(1.0, -1.0, 0.0), vec3(-1.0, -1.0, 0.0),
+    vec3(1.0, 0.0, 1.0), vec3(-1.0, 0.0, 1.0), vec3(1.0, 0.0, -1.0), vec3(-1.0, 0.0, -1.0),
+    vec3(0.0, 1.0, 1.0), vec3(0.0, -1.0, 1.0), vec3(0.0, 1.0, -1.0), vec3(0.0, -1.0, -1.0)
+);
+
+// Integer hash with good avalanche, the `lowbias32` mix.
+uint noise_hash(uint x) {
+    x ^= x >> 16;
+    x *= 0x7feb352du;
+    x ^= x >> 15;
+    x *= 0x846ca68bu;
+    return x ^ (x >> 16);
+}
+
+// Hash of a lattice cell already wrapped into the tile.
+uint noise_hash_cell(ivec3 cell, uint seed) {
+    return noise_hash(uint(cell.x) ^ noise_hash(uint(cell.y) ^ noise_hash(uint(cell.z) ^ noise_hash(seed))));
+}
+
+float noise_unit(uint h) {
+    return float(h >> 8) / 16777216.0;
+}
+
+// `%` is undefined for negative operands, so shift cells to positive ones first.
+ivec2 noise_wrap(ivec2 cell, int period) {
+    return (cell + 16 * period) % period;
+}
+
+ivec3 noise_wrap(ivec3 cell, int period) {
+    return (cell + 16 * period) % period;
+}
+
+vec2 noise_fade(vec2 t) {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+vec3 noise_fade(vec3 t) {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+float noise_perlin_corner(ivec2 cell, ivec2 offset, vec2 f, int period, uint seed) {
+    ivec2 wrapped = noise_wrap(cell + offset, period);
+    vec2 gradient = NOISE_GRADIENTS_2D[noise_hash_cell(ivec3(wrapped, 0), seed) & 7u];
+    return dot(gradient, f - vec2(offset));
+}
+
+float noise_perlin_corner(ivec3 cell, ivec3 offset, vec3 f, int period, uint seed) {
+    ivec3 wrapped = noise_wrap(cell + offset, period);
+    vec3 gradient = NOISE_GRADIENTS_3D[noise_hash_cell(wrapped, seed) % 12u];
+    return dot(gradient, f - vec3(offset));
+}
+
+float noise_perlin(vec2 p, int period, uint seed) {
+    vec2 f = fract(p);
+    ivec2 cell = ivec2(floor(p));
+    vec2 u = noise_fade(f);
+
+    float bottom = mix(
+        noise_perlin_corner(cell, ivec2(0, 0), f, period, seed),
+        noise_perlin_corner(cell, ivec2(1, 0), f, period, seed),
+        u.x
+    );
+    float top = mix(
+        noise_perlin_corner(cell, ivec2(0, 1), f, period, seed),
+        noise_perlin_corner(cell, ivec2(1, 1), f, period, seed),
+        u.x
+    );
+    return mix(bottom, top, u.y) * 1.41421356;
+}
+
+float noise_perlin(vec3 p, int period, uint seed) {
+    vec3 f = fract(p);
+    ivec3 cell = ivec3(floor(p));
+    vec3 u = noise_fade(f);
+
+    float front = mix(
+        mix(noise_perlin_corner(cell, ivec3(0, 0, 0), f, period, seed),
+            noise_perlin_corner(cell, ivec3(1, 0, 0), f, period, seed), u.x),
+        mix(noise_perlin_corner(cell, ivec3(0, 1, 0), f, period, seed),
+            noise_perlin_corner(cell, ivec3(1, 1, 0), f, period, seed), u.x),
+        u.y
+    );
+    float back = mix(
+        mix(noise_perlin_corner(cell, ivec3(0, 0, 1), f, period, seed),
+            noise_perlin_corner(cell, ivec3(1, 0, 1), f, period, seed), u.x),
+        mix(noise_perlin_corner(cell, ivec3(0, 1, 1), f, period, seed),
+            noise_perlin_corner(cell, ivec3(1, 1, 1), f, period, seed), u.x),
+        u.y
+    );
+    return mix(front, back, u.z);
+}
+
+// Simplex noise on the lattice of `psrdnoise`, whose vertices `(i.x - i.y / 2, i.y)` repeat
+// horizontally every cell and vertically every two cells.
+float noise_simplex(vec2 p, int period, uint seed) {
+    vec2 skewed = vec2(p.x + p.y * 0.5, p.y);
+    vec2 f = fract(skewed);
+    ivec2 base = ivec2(floor(skewed));
+    ivec2 middle = f.x > f.y ? ivec2(1, 0) : ivec2(0, 1);
+    ivec2 corners[3] = ivec2[3](base, base + middle, base + ivec2(1));
+
+    float noise = 0.0;
+    for (int k = 0; k < 3; k++) {
+        ivec2 i = corners[k];
+        // Vertices are half-integer, their doubles wrap exactly.
+        ivec2 doubled = ivec2(2 * i.x - i.y, 2 * i.y);
+        vec2 d = p - vec2(doubled) * 0.5;
+        ivec2 key = noise_wrap(doubled, 2 * period);
+        vec2 gradient = NOISE_GRADIENTS_2D[noise_hash_cell(ivec3(key, 0), seed) & 7u];
+        float weight = max(0.8 - dot(d, d), 0.0);
+        weight *= weight;
+        noise += weight * weight * dot(gradient, d);
+    }
+    return noise * NOISE_SIMPLEX_2D_SCALE;
+}
+
+// Simplex noise on the body-centered cubic lattice of `psrdnoise`, which repeats every cell
+// along each axis.
+float noise_simplex(vec3 p, int period, uint seed) {
+    vec3 skewed = vec3(p.y + p.z, p.x + p.z, p.x + p.y);
+    vec3 f = fract(skewed);
+    ivec3 base = ivec3(floor(skewed));
+
+    // Walk from the base corner along the axes by decreasing fractional part.
+    ivec3 largest = f.x >= f.y && f.x >= f.z ? ivec3(1, 0, 0) : f.y >= f.z ? ivec3(0, 1, 0) : ivec3(0, 0, 1);
+    ivec3 smallest = f.x < f.y && f.x < f.z ? ivec3(1, 0, 0) : f.y < f.z ? ivec3(0, 1, 0) : ivec3(0, 0, 1);
+    ivec3 corners[4] = ivec3[4](base, base + largest, base + ivec3(1) - smallest, base + ivec3(1));
+
+    float noise = 0.0;
+    for (int k = 0; k < 4; k++) {
+        ivec3 i = corners[k];
+        ivec3 doubled = ivec3(-i.x + i.y + i.z, i.x - i.y + i.z, i.x + i.y - i.z);
+        vec3 d = p - vec3(doubled) * 0.5;
+        ivec3 key = noise_wrap(doubled, 2 * period);
+        vec3 gradient = NOISE_GRADIENTS_3D[noise_hash_cell(key, seed) % 12u];
+        float weight = max(0.5 - dot(d, d), 0.0);
+        noise += weight * weight * weight * dot(gradient, d);
+    }
+    return noise * NOISE_SIMPLEX_3D_SCALE;
+}
+
+float noise_worley(vec2 p, int period, uint seed) {
+    ivec2 cell = ivec2(floor(p));
+    float closest = 1e30;
+    for (int y = -1; y <= 1; y++) {
+        for (int x = -1; x <= 1; x++) {
+            ivec2 neighbor = cell + ivec2(x, y);
+            uint h = noise_hash_cell(ivec3(noise_wrap(neighbor, period), 0), seed);
+            vec2 point = vec2(neighbor) + vec2(noise_unit(h), noise_unit(noise_hash(h)));
+            vec2 d = point - p;
+            closest = min(closest, dot(d, d));
+        }
+    }
+    return sqrt(closest);
+}
+
+float noise_worley(vec3 p, int period, uint seed) {
+    ivec3 cell = ivec3(floor(p));
+    float closest = 1e30;
+    for (int z = -1; z <= 1; z++) {
+        for (int y = -1; y <= 1; y++) {
+            for (int x = -1; x <= 1; x++) {
+                ivec3 neighbor = cell + ivec3(x, y, z);
+                uint h = noise_hash_cell(noise_wrap(neighbor, period), seed);
+                vec3 jitter = vec3(noise_unit(h), noise_unit(noise_hash(h)), noise_unit(noise_hash(noise_hash(h))));
+                vec3 d = vec3(neighbor) + jitter - p;
+                closest = min(closest, dot(d, d));
+            }
+        }
+    }
+    return sqrt(closest);
+}
+
+float noise_single(uint kind, vec2 p, int period, uint seed) {
+    switch (kind) {
+    case NOISE_SIMPLEX:
+        return noise_simplex(p, period, seed);
+    case NOISE_WORLEY:
+        return noise_worley(p, period, seed);
+    default:
+        return noise_perlin(p, period, seed);
+    }
+}
+
+float noise_single(uint kind, vec3 p, int period, uint seed) {
+    switch (kind) {
+    case NOISE_SIMPLEX:
+        return noise_simplex(p, period, seed);
+    case NOISE_WORLEY:
+        return noise_worley(p, period, seed);
+    default:
+        return noise_perlin(p, period, seed);
+    }
+}
+
+// fBm value at `p`, normalized by the total amplitude of the octaves.
+float noise_fbm(NoiseParams params, vec2 p, uint seed) {
+    float sum = 0.0;
+    float amplitude = 1.0;
+    float total = 0.0;
+    for (uint octave = 0u; octave < max(params.octaves, 1u); octave++) {
+        int period = int(params.period << octave);
+        sum += amplitude * noise_single(params.kind, p * float(period), period, seed + octave);
+        total += amplitude;
+        amplitude *= params.gain;
+    }
+    return sum / total;
+}
+
+float noise_fbm(NoiseParams params, vec3 p, uint seed) {
+    float sum = 0.0;
+    float amplitude = 1.0;
+    float total = 0.0;
+    for (uint octave = 0u; octave < max(params.octaves, 1u); octave++) {
+        int period = int(params.period << octave);
+        sum += amplitude * noise_single(params.kind, p * float(period), period, seed + octave);
+        total += amplitude;
+        amplitude *= params.gain;
+    }
+    return sum / total;
+}
+
+// Curl of the fBm taken as a stream function, with distances in cells of the first octave.
+vec2 noise_curl(NoiseParams params, vec2 p) {
+    vec2 e = vec2(NOISE_CURL_EPSILON, 0.0);
+    float scale = 2.0 * NOISE_CURL_EPSILON * float(params.period);
+    float dx = (noise_fbm(params, p + e.xy, params.seed) - noise_fbm(params, p - e.xy, params.seed)) / scale;
+    float dy = (noise_fbm(params, p + e.yx, params.seed) - noise_fbm(params, p - e.yx, params.seed)) / scale;
+    return vec2(dy, -dx);
+}
+
+// Partial derivative of curl potential component `i` along `axis`.
+float noise_potential_derivative(NoiseParams params, vec3 p, uint i, vec3 axis) {
+    uint seed = params.seed + NOISE_CURL_SEEDS[i];
+    vec3 offset = axis * NOISE_CURL_EPSILON;
+    return (noise_fbm(params, p + offset, seed) - noise_fbm(params, p - offset, seed))
+        / (2.0 * NOISE_CURL_EPSILON * float(params.period));
+}
+
+// Curl of a vector potential made of three fBm fields with different seeds, with distances
+// in cells of the first octave.
+vec3 noise_curl(NoiseParams params, vec3 p) {
+    vec3 x = vec3(1.0, 0.0, 0.0);
+    vec3 y = vec3(0.0, 1.0, 0.0);
+    vec3 z = vec3(0.0, 0.0, 1.0);
+    return vec3(
+        noise_potential_derivative(params, p, 2u, y) - noise_potential_derivative(params, p, 1u, z),
+        noise_potential_derivative(params, p, 0u, z) - noise_potential_derivative(params, p, 2u, x),
+        noise_potential_derivative(params, p, 1u, x) - noise_potential_derivative(params, p, 0u, y)
+    );
+}
@@ -0,0 +1,35 @@
+#version 450
+
+// Fill a 2D image with tileable noise, see `NoiseGenerator`.
+
+#include "../noise.glsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(rgba16f, binding = 0) uniform writeonly image2D noise_image;
+
+uniform uint noise_kind;
+uniform uint noise_output;
+uniform uint noise_seed;
+uniform uint noise_period;
+uniform uint noise_octaves;
+uniform float noise_gain;
+
+void main() {
+    ivec2 size = imageSize(noise_image);
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(pixel, size))) {
+        return;
+    }
+
+    NoiseParams params = NoiseParams(noise_kind, noise_seed, noise_period, noise_octaves, noise_gain);
+    vec2 p = (vec2(pixel) + 0.5) / vec2(size);
+
+    vec4 value;
+    if (noise_output == NOISE_CURL) {
+        value = vec4(noise_curl(params, p), 0.0, 1.0);
+    } else {
+        value = vec4(vec3(noise_fbm(params, p, noise_seed)), 1.0);
+    }
+    imageStore(noise_image, pixel, value);
+}
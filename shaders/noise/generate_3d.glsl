@@ -0,0 +1,35 @@
+#version 450
+
+// Fill a 3D image with tileable noise, see `NoiseGenerator`.
+
+#include "../noise.glsl"
+
+layout(local_size_x = 4, local_size_y = 4, local_size_z = 4) in;
+
+layout(rgba16f, binding = 0) uniform writeonly image3D noise_image;
+
+uniform uint noise_kind;
+uniform uint noise_output;
+uniform uint noise_seed;
+uniform uint noise_period;
+uniform uint noise_octaves;
+uniform float noise_gain;
+
+void main() {
+    ivec3 size = imageSize(noise_image);
+    ivec3 voxel = ivec3(gl_GlobalInvocationID);
+    if (any(greaterThanEqual(voxel, size))) {
+        return;
+    }
+
+    NoiseParams params = NoiseParams(noise_kind, noise_seed, noise_period, noise_octaves, noise_gain);
+    vec3 p = (vec3(voxel) + 0.5) / vec3(size);
+
+    vec4 value;
+    if (noise_output == NOISE_CURL) {
+        value = vec4(noise_curl(params, p), 1.0);
+    } else {
+        value = vec4(vec3(noise_fbm(params, p, noise_seed)), 1.0);
+    }
+    imageStore(noise_image, voxel, value);
+}
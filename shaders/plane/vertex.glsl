@@ -1,14 +1,6 @@
 #version 450
 
-struct FrameData {
-    mat4 view_proj;
-
-    vec3 sun_dir;
-    uint point_light_count;
-
-    vec3 sun_color;
-    float padding_1;
-};
+#include "../structs.glsl"
 
 layout(location = 0) uniform FrameData frame_data;
 
@@ -16,11 +8,21 @@ layout(location = 0) in vec3 pos;
 layout(location = 1) in vec3 normal;
 layout(location = 2) in vec3 color;
 
+layout(location = 8) in mat4 instance_transform;
+layout(location = 12) in vec4 instance_color;
+
+uniform uint instanced;
+uniform mat4 model;
+uniform vec4 object_color;
+
 out vec3 vNormal;
 out vec3 vColor;
 
 void main() {
-  gl_Position = vec4(1.);// frame_data.view_proj * vec4(pos, 1.);
-  vNormal = normal;
-  vColor = color;
+  mat4 model_matrix = instanced != 0u ? instance_transform : model;
+  vec4 tint = instanced != 0u ? instance_color : object_color;
+
+  gl_Position = vec4(1.);// frame_data.view_proj * model_matrix * vec4(pos, 1.);
+  vNormal = mat3(model_matrix) * normal;
+  vColor = color * tint.rgb;
 }
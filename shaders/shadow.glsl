@@ -0,0 +1,45 @@
+// Cascaded shadow map sampling, for inclusion after `structs.glsl`.
+
+layout(std140, binding = 1) uniform ShadowBlock {
+    ShadowData shadow_data;
+};
+
+layout(binding = 15) uniform sampler2DArrayShadow shadow_map;
+
+// Index of the cascade covering a point `view_distance` away from the camera, or
+// `cascade_count` past the last one.
+uint shadow_cascade(float view_distance) {
+    uint cascade = 0u;
+    while (cascade < shadow_data.cascade_count && view_distance > shadow_data.split_distances[cascade]) {
+        cascade++;
+    }
+    return cascade;
+}
+
+// Fraction of sun light reaching `world_pos`, filtered over a 3x3 texel area.
+float shadow_visibility(vec3 world_pos, vec3 world_normal, float view_distance) {
+    uint cascade = shadow_cascade(view_distance);
+    if (cascade >= shadow_data.cascade_count) {
+        return 1.;
+    }
+
+    // Push the sample along the normal to avoid self-shadowing on sloped surfaces.
+    vec3 offset = world_normal * shadow_data.normal_bias * shadow_data.texel_sizes[cascade];
+    vec4 light_pos = shadow_data.light_view_proj[cascade] * vec4(world_pos + offset, 1.);
+    vec3 coords = light_pos.xyz / light_pos.w;
+    coords.xy = coords.xy * 0.5 + 0.5;
+
+    // Reverse-Z: larger depths are closer to the light.
+    float reference = coords.z + shadow_data.depth_bias;
+
+    vec2 texel = 1. / vec2(textureSize(shadow_map, 0).xy);
+    float visibility = 0.;
+    for (int y = -1; y <= 1; y++) {
+        for (int x = -1; x <= 1; x++) {
+            vec2 uv = coords.xy + vec2(x, y) * texel;
+            visibility += texture(shadow_map, vec4(uv, float(cascade), reference));
+        }
+    }
+
+    return visibility / 9.;
+}
@@ -0,0 +1,4 @@
+#version 450
+
+// Casters only write depth, so there is nothing to shade.
+void main() {}
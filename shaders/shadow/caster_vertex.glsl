@@ -0,0 +1,17 @@
+#version 450
+
+// Depth-only pass rendering shadow casters into a cascade, see `CascadedShadowMap`. Only
+// positions and transforms are read, whatever the vertex format.
+
+layout(location = 0) in vec3 pos;
+
+layout(location = 8) in mat4 instance_transform;
+
+uniform uint instanced;
+uniform mat4 model;
+uniform mat4 light_view_proj;
+
+void main() {
+  mat4 model_matrix = instanced != 0u ? instance_transform : model;
+  gl_Position = light_view_proj * model_matrix * vec4(pos, 1.);
+}
@@ -16,3 +16,16 @@ struct PointLight {
     vec3 color;
     float padding_1;
 };
+
+struct ShadowData {
+    mat4 light_view_proj[4];
+    // View distance at which each cascade ends.
+    vec4 split_distances;
+    // World-space size of a shadow map texel in each cascade.
+    vec4 texel_sizes;
+
+    uint cascade_count;
+    float depth_bias;
+    float normal_bias;
+    float padding_1;
+};
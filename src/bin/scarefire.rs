@@ -1,29 +1,25 @@
 use std::rc::Rc;
 
-use glam::vec3;
-use glfw::{Action, Context, Key};
+use glam::{uvec2, vec2, vec3, vec4, Mat4, Vec3};
+use glfw::{Action, Context, Key, MouseButton};
 use scarefire::prelude::*;
 
 fn init_scene() -> Scene {
     let mut scene = Scene::default();
 
-    let vertices = [
-        vec3(-1., -1., 0.),
-        vec3(-1., 1., 0.),
-        vec3(1., 1., 0.),
-        vec3(1., -1., 0.),
-    ]
-    .into_iter()
-    .map(|position| Vertex {
-        position,
-        normal: position / 2. + 1.,
-        color: vec3(1., 1., 1.),
-    })
-    .collect::<Vec<_>>();
+    let mut data = MeshData::new(
+        vec![
+            vec3(-1., -1., 0.),
+            vec3(-1., 1., 0.),
+            vec3(1., 1., 0.),
+            vec3(1., -1., 0.),
+        ],
+        vec![0, 1, 2, 2, 3, 0],
+    );
+    data.compute_smooth_normals();
 
-    let indices = [0, 1, 2, 2, 3, 0];
-
-    let mesh = StaticMesh::new(vertices.as_slice(), &indices);
+    let mesh =
+        StaticMesh::<Vertex>::from_data(&data).with_pick_geometry(PickGeometry::from_data(&data));
 
     let program = Program::new_shader(ShaderPaths {
         fragment: "shaders/plane/fragment.glsl".to_owned(),
@@ -38,20 +34,111 @@ fn init_scene() -> Scene {
     let obj = SceneObject::new(Rc::new(mesh), Rc::new(material));
 
     scene.add_object(obj);
+    scene.enable_shadows(ShadowSettings {
+        cascade_count: 3,
+        ..Default::default()
+    });
 
     scene
 }
 
+/// Slow push toward the fire, narrowing the field of view as it goes.
+fn dolly_in(from: Vec3) -> CameraPath {
+    let mut path = CameraPath::new(Interpolation::CatmullRom);
+    let fov = 70f32.to_radians();
+    path.add_keyframe(Keyframe::looking_at(0., from, Vec3::ZERO, fov).with_easing(Easing::EaseIn));
+    path.add_keyframe(
+        Keyframe::looking_at(4., from * 0.7 + Vec3::X * 0.3, Vec3::ZERO, fov)
+            .with_easing(Easing::EaseOut),
+    );
+    path.add_keyframe(Keyframe::looking_at(8., from * 0.4, Vec3::ZERO, fov * 0.8));
+    path
+}
+
 fn main() {
     println!("OooOooOOoOOOoo");
 
     let (mut glfw, (mut window, events)) = unsafe { init_graphics() };
 
-    let scene = init_scene();
+    let mut scene = init_scene();
+
+    let eye = scene.camera.position();
+    let mut controller: Box<dyn CameraController> = Box::new(FireplaceViewer::new(eye, Vec3::ZERO));
+    let mut last_time = glfw.get_time();
+    let mut take_screenshot = false;
+    let mut msaa: Option<Rc<Framebuffer>> = None;
+
+    let mut security_camera = Camera::new();
+    security_camera.set_view(&Mat4::look_at_rh(vec3(3., 4., -2.), Vec3::ZERO, Vec3::Y));
 
     // Loop until the user closes the window
     while !window.should_close() {
-        scene.render();
+        let time = glfw.get_time();
+        controller.update(&mut scene.camera, (time - last_time) as f32);
+        last_time = time;
+
+        let (width, height) = window.get_framebuffer_size();
+        let target_size = uvec2(width as u32, height as u32);
+
+        // Minimized windows have no pixels, so wait for them to come back before rendering
+        // or recreating the framebuffer. Queued events are handled after the next frame.
+        if target_size.min_element() == 0 {
+            glfw.wait_events();
+            continue;
+        }
+
+        // Render the main view multisampled, then resolve it into the window.
+        if msaa.as_ref().map(|fb| fb.size()) != Some(target_size) {
+            let framebuffer = Framebuffer::multisampled(
+                target_size,
+                ImageFormat::Rgba8SRgb,
+                ImageFormat::Depth24Stencil8,
+                4,
+                vec4(0.5, 0.7, 0.8, 0.),
+            )
+            .expect("Could not create the multisampled framebuffer");
+            msaa = Some(Rc::new(framebuffer));
+        }
+        let msaa_target = msaa.clone().unwrap();
+
+        let mut main_view = View::full(
+            scene.camera.clone(),
+            ViewTarget::Framebuffer(msaa_target.clone()),
+        );
+        main_view.fit_camera();
+        let stats = scene.render_view(&main_view);
+        msaa_target.blit_to_window(target_size);
+
+        // Picture-in-picture security camera in the top-right corner.
+        let inset_size = target_size / 4;
+        let mut inset = View::new(
+            security_camera.clone(),
+            Viewport::new(
+                (target_size.x - inset_size.x) as i32 - 10,
+                10,
+                inset_size.x,
+                inset_size.y,
+            ),
+            ViewTarget::Default(target_size),
+        );
+        inset.clear_color = Some(vec4(0.1, 0.1, 0.1, 1.));
+        inset.fit_camera();
+        scene.render_view(&inset);
+        window.set_title(&format!(
+            "ScareFire ({} visible, {} culled)",
+            stats.visible, stats.culled
+        ));
+
+        // The back buffer is only valid until swapped.
+        if take_screenshot {
+            take_screenshot = false;
+            let window = ViewTarget::Default(target_size);
+            let pixels = window.read_pixels(&Viewport::full(target_size.x, target_size.y));
+            match pixels.save_png("screenshot.png") {
+                Ok(()) => println!("Saved screenshot.png"),
+                Err(err) => println!("Could not save screenshot: {err}"),
+            }
+        }
 
         // Swap front and back buffers
         window.swap_buffers();
@@ -59,12 +146,38 @@ fn main() {
         // Poll for and process events
         glfw.poll_events();
         for (_, event) in glfw::flush_messages(&events) {
-            println!("{:?}", event);
             match event {
                 glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
                     window.set_should_close(true)
                 }
-                _ => {}
+                glfw::WindowEvent::Key(Key::Num1, _, Action::Press, _) => {
+                    controller = Box::new(FireplaceViewer::new(eye, Vec3::ZERO))
+                }
+                glfw::WindowEvent::Key(Key::Num2, _, Action::Press, _) => {
+                    controller = Box::new(FlyController::new(scene.camera.position(), Vec3::ZERO))
+                }
+                glfw::WindowEvent::Key(Key::Num3, _, Action::Press, _) => {
+                    controller = Box::new(OrbitController::new(scene.camera.position(), Vec3::ZERO))
+                }
+                glfw::WindowEvent::Key(Key::Num4, _, Action::Press, _) => {
+                    controller = Box::new(CameraPathPlayer::new(dolly_in(eye)))
+                }
+                glfw::WindowEvent::Key(Key::F12, _, Action::Press, _) => take_screenshot = true,
+                glfw::WindowEvent::MouseButton(MouseButton::Button3, Action::Press, _) => {
+                    let (x, y) = window.get_cursor_pos();
+                    // Only the view's copy of the camera has the window's aspect ratio.
+                    let (width, height) = window.get_size();
+                    let ray = main_view.camera.screen_ray(
+                        vec2(x as f32, y as f32),
+                        &Viewport::full(width as u32, height as u32),
+                    );
+
+                    match scene.pick(&ray, PickMode::Triangles) {
+                        Some(hit) => println!("Picked object at {}", hit.point),
+                        None => println!("Picked nothing"),
+                    }
+                }
+                _ => controller.handle_event(&event),
             }
         }
     }
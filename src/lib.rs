@@ -6,7 +6,10 @@ pub use wrapper::*;
 pub mod prelude {
     pub use super::{
         wrapper::{
-            buffer::*, camera::*, handle::*, material::*, mesh::*, program::*, scene::*, texture::*,
+            bounds::*, buffer::*, camera::*, camera_path::*, controller::*, flipbook::*,
+            framebuffer::*, handle::*, image::*, material::*, mesh::*, mesh_data::*, noise::*,
+            program::*, sampler::*, scene::*, shadow::*, texture::*, vertex::*, view::*,
+            watcher::*,
         },
         *,
     };
@@ -83,6 +86,9 @@ pub unsafe fn init_graphics() -> (Glfw, (PWindow, GlfwReceiver<(f64, WindowEvent
     // Make the window's context current
     window.make_current();
     window.set_key_polling(true);
+    window.set_cursor_pos_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_scroll_polling(true);
 
     load_with(|f_name| window.get_proc_address(f_name));
 
@@ -0,0 +1,558 @@
+//! CPU decoding of block-compressed images, for drivers lacking a format.
+//!
+//! Every decoder turns one 4x4 block into 16 RGBA8 pixels, row by row.
+
+use super::texture::ImageFormat;
+
+type Block = [[u8; 4]; 16];
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let r = (color >> 11) as u8 & 0x1f;
+    let g = (color >> 5) as u8 & 0x3f;
+    let b = color as u8 & 0x1f;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+/// Color half of BC1 and BC3 blocks. BC3 always uses the four color palette.
+fn decode_bc1(bytes: &[u8], always_opaque: bool) -> Block {
+    let c0 = u16::from_le_bytes([bytes[0], bytes[1]]);
+    let c1 = u16::from_le_bytes([bytes[2], bytes[3]]);
+    let indices = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+    let [e0, e1] = [rgb565(c0), rgb565(c1)];
+    let mix = |a: u32, b: u32, total: u32| {
+        std::array::from_fn::<u8, 3, _>(|i| {
+            ((e0[i] as u32 * a + e1[i] as u32 * b + total / 2) / total) as u8
+        })
+    };
+
+    let with_alpha = |[r, g, b]: [u8; 3]| [r, g, b, 255];
+    let palette = if c0 > c1 || always_opaque {
+        [
+            with_alpha(e0),
+            with_alpha(e1),
+            with_alpha(mix(2, 1, 3)),
+            with_alpha(mix(1, 2, 3)),
+        ]
+    } else {
+        [
+            with_alpha(e0),
+            with_alpha(e1),
+            with_alpha(mix(1, 1, 2)),
+            [0, 0, 0, 0],
+        ]
+    };
+
+    std::array::from_fn(|i| palette[(indices >> (2 * i)) as usize & 3])
+}
+
+/// Single channel block, the alpha of BC3 and the channels of BC4 and BC5.
+fn decode_bc4(bytes: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (bytes[0] as u32, bytes[1] as u32);
+    let indices = bytes[2..8]
+        .iter()
+        .rev()
+        .fold(0u64, |bits, &byte| (bits << 8) | byte as u64);
+
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 255];
+    if a0 > a1 {
+        for k in 1..7 {
+            palette[k + 1] = ((7 - k as u32) * a0 + k as u32 * a1 + 3) / 7;
+        }
+    } else {
+        for k in 1..5 {
+            palette[k + 1] = ((5 - k as u32) * a0 + k as u32 * a1 + 2) / 5;
+        }
+    }
+
+    std::array::from_fn(|i| palette[(indices >> (3 * i)) as usize & 7] as u8)
+}
+
+/// Reads a BC7 block's fields from the least significant bit up.
+struct BitReader {
+    bits: u128,
+    position: u32,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u8 {
+        if count == 0 {
+            return 0;
+        }
+        let value = (self.bits >> self.position) as u8 & ((1u16 << count) - 1) as u8;
+        self.position += count;
+        value
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    /// One p-bit per endpoint.
+    endpoint_p_bits: bool,
+    /// One p-bit shared by both endpoints of a subset.
+    shared_p_bits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn mode(
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_p_bits: bool,
+    shared_p_bits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+) -> Bc7Mode {
+    Bc7Mode {
+        subsets,
+        partition_bits,
+        rotation_bits,
+        index_selection_bits,
+        color_bits,
+        alpha_bits,
+        endpoint_p_bits,
+        shared_p_bits,
+        index_bits,
+        secondary_index_bits,
+    }
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    mode(3, 4, 0, 0, 4, 0, true, false, 3, 0),
+    mode(2, 6, 0, 0, 6, 0, false, true, 3, 0),
+    mode(3, 6, 0, 0, 5, 0, false, false, 2, 0),
+    mode(2, 6, 0, 0, 7, 0, true, false, 2, 0),
+    mode(1, 0, 2, 1, 5, 6, false, false, 2, 3),
+    mode(1, 0, 2, 0, 7, 8, false, false, 2, 2),
+    mode(1, 0, 0, 0, 7, 7, true, false, 4, 0),
+    mode(2, 6, 0, 0, 5, 5, true, false, 2, 0),
+];
+
+/// Subset of each pixel for the two subset partitions, one bit per pixel.
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80, 0xc800, 0xffec, 0xfe80, 0xe800,
+    0xffe8, 0xff00, 0xfff0, 0xf000, 0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c, 0xaaaa, 0xf0f0, 0x5a5a, 0x33cc,
+    0x3c3c, 0x55aa, 0x9696, 0xa55a, 0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c, 0x9336, 0x9cc6, 0x817e, 0xe718,
+    0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+/// Subset of each pixel for the three subset partitions, two bits per pixel.
+const BC7_PARTITIONS_3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+
+/// Pixel holding the second subset's anchor index in the two subset partitions.
+const BC7_ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Pixels holding the second and third subsets' anchor indices in the three subset partitions.
+#[rustfmt::skip]
+const BC7_ANCHORS_3: [[u8; 2]; 64] = [
+    [3, 15], [3, 8], [15, 8], [15, 3], [8, 15], [3, 15], [15, 3], [15, 8],
+    [8, 15], [8, 15], [6, 15], [6, 15], [6, 15], [5, 15], [3, 15], [3, 8],
+    [3, 15], [3, 8], [8, 15], [15, 3], [3, 15], [3, 8], [6, 15], [10, 8],
+    [5, 3], [8, 15], [8, 6], [6, 10], [8, 15], [5, 15], [15, 10], [15, 8],
+    [8, 15], [15, 3], [3, 15], [5, 10], [6, 10], [10, 8], [8, 9], [15, 10],
+    [15, 6], [3, 15], [15, 8], [5, 15], [15, 3], [15, 6], [15, 6], [15, 8],
+    [3, 15], [15, 3], [5, 15], [5, 15], [5, 15], [8, 15], [5, 15], [10, 15],
+    [5, 15], [10, 15], [8, 15], [13, 15], [15, 3], [12, 15], [3, 15], [3, 8],
+];
+
+const BC7_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn bc7_interpolate(e0: u8, e1: u8, index: u8, bits: u32) -> u8 {
+    let weight = match bits {
+        2 => BC7_WEIGHTS_2[index as usize],
+        3 => BC7_WEIGHTS_3[index as usize],
+        _ => BC7_WEIGHTS_4[index as usize],
+    };
+    (((64 - weight) * e0 as u32 + weight * e1 as u32 + 32) >> 6) as u8
+}
+
+fn decode_bc7(bytes: &[u8]) -> Block {
+    let mut reader = BitReader {
+        bits: u128::from_le_bytes(bytes[..16].try_into().unwrap()),
+        position: 0,
+    };
+
+    let Some(mode_index) = (0..8).find(|_| reader.read(1) == 1) else {
+        // Reserved mode, decoded as transparent black.
+        return [[0; 4]; 16];
+    };
+    let mode = &BC7_MODES[mode_index];
+
+    let partition = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_selection = reader.read(mode.index_selection_bits);
+
+    let subset_of = |pixel: usize| match mode.subsets {
+        2 => (BC7_PARTITIONS_2[partition] >> pixel) as usize & 1,
+        3 => (BC7_PARTITIONS_3[partition] >> (2 * pixel)) as usize & 3,
+        _ => 0,
+    };
+    let is_anchor = |pixel: usize| match mode.subsets {
+        2 => pixel == 0 || pixel == BC7_ANCHORS_2[partition] as usize,
+        3 => pixel == 0 || BC7_ANCHORS_3[partition].contains(&(pixel as u8)),
+        _ => pixel == 0,
+    };
+
+    // Channels of both endpoints of every subset, all reds first.
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u8; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[channel] = reader.read(mode.color_bits);
+        }
+    }
+    if mode.alpha_bits > 0 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[3] = reader.read(mode.alpha_bits);
+        }
+    }
+
+    let (mut color_bits, mut alpha_bits) = (mode.color_bits, mode.alpha_bits);
+    if mode.endpoint_p_bits || mode.shared_p_bits {
+        let p_bits: Vec<u8> = if mode.endpoint_p_bits {
+            (0..endpoint_count).map(|_| reader.read(1)).collect()
+        } else {
+            (0..mode.subsets)
+                .flat_map(|_| {
+                    let bit = reader.read(1);
+                    [bit, bit]
+                })
+                .collect()
+        };
+
+        for (endpoint, p_bit) in endpoints.iter_mut().zip(p_bits) {
+            for channel in endpoint.iter_mut() {
+                *channel = (*channel << 1) | p_bit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    // Replicate the high bits into the low ones, so that 0 and the maximum stay exact.
+    let expand = |value: u8, bits: u32| {
+        if bits == 8 {
+            return value;
+        }
+        let value = value << (8 - bits);
+        value | (value >> bits)
+    };
+    for endpoint in &mut endpoints[..endpoint_count] {
+        for channel in &mut endpoint[..3] {
+            *channel = expand(*channel, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 {
+            expand(endpoint[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    // Anchor indices drop their implied most significant bit.
+    let mut read_indices = |bits: u32| -> [u8; 16] {
+        std::array::from_fn(|pixel| reader.read(bits - is_anchor(pixel) as u32))
+    };
+    let indices = read_indices(mode.index_bits);
+    let secondary_indices =
+        (mode.secondary_index_bits > 0).then(|| read_indices(mode.secondary_index_bits));
+
+    std::array::from_fn(|pixel| {
+        let subset = subset_of(pixel);
+        let [e0, e1] = [endpoints[2 * subset], endpoints[2 * subset + 1]];
+
+        let (color_index, color_bits, alpha_index, alpha_bits) = match secondary_indices {
+            Some(secondary) if index_selection == 1 => (
+                secondary[pixel],
+                mode.secondary_index_bits,
+                indices[pixel],
+                mode.index_bits,
+            ),
+            Some(secondary) => (
+                indices[pixel],
+                mode.index_bits,
+                secondary[pixel],
+                mode.secondary_index_bits,
+            ),
+            None => (
+                indices[pixel],
+                mode.index_bits,
+                indices[pixel],
+                mode.index_bits,
+            ),
+        };
+
+        let mut color: [u8; 4] = std::array::from_fn(|channel| {
+            if channel < 3 {
+                bc7_interpolate(e0[channel], e1[channel], color_index, color_bits)
+            } else {
+                bc7_interpolate(e0[3], e1[3], alpha_index, alpha_bits)
+            }
+        });
+        if rotation > 0 {
+            color.swap(3, rotation as usize - 1);
+        }
+        color
+    })
+}
+
+fn decode_block(format: ImageFormat, bytes: &[u8]) -> Block {
+    match format {
+        ImageFormat::Bc1RgbaUnorm | ImageFormat::Bc1RgbaSRgb => decode_bc1(bytes, false),
+        ImageFormat::Bc3RgbaUnorm | ImageFormat::Bc3RgbaSRgb => {
+            let alpha = decode_bc4(&bytes[..8]);
+            let mut block = decode_bc1(&bytes[8..], true);
+            for (pixel, alpha) in block.iter_mut().zip(alpha) {
+                pixel[3] = alpha;
+            }
+            block
+        }
+        ImageFormat::Bc4RUnorm => decode_bc4(bytes).map(|r| [r, 0, 0, 255]),
+        ImageFormat::Bc5RgUnorm => {
+            let (red, green) = (decode_bc4(&bytes[..8]), decode_bc4(&bytes[8..]));
+            std::array::from_fn(|i| [red[i], green[i], 0, 255])
+        }
+        ImageFormat::Bc7RgbaUnorm | ImageFormat::Bc7RgbaSRgb => decode_bc7(bytes),
+        _ => panic!("{format:?} is not block-compressed"),
+    }
+}
+
+/// Decode a `size` image of `format` into RGBA8 pixels. Single and dual channel formats fill
+/// the missing channels like GL does, with 0 for color and 255 for alpha.
+pub(crate) fn decompress(format: ImageFormat, data: &[u8], size: glam::UVec2) -> Vec<u8> {
+    let block_bytes = format
+        .block_bytes()
+        .unwrap_or_else(|| panic!("{format:?} is not block-compressed"));
+    let (width, height) = (size.x as usize, size.y as usize);
+    let blocks_wide = width.div_ceil(4);
+
+    let mut pixels = vec![0; width * height * 4];
+    for (i, bytes) in data.chunks_exact(block_bytes).enumerate() {
+        let (block_x, block_y) = (i % blocks_wide * 4, i / blocks_wide * 4);
+
+        for (j, texel) in decode_block(format, bytes).iter().enumerate() {
+            let (x, y) = (block_x + j % 4, block_y + j / 4);
+            // Blocks of images that are not a multiple of 4 overhang the edges.
+            if x < width && y < height {
+                let offset = (y * width + x) * 4;
+                pixels[offset..offset + 4].copy_from_slice(texel);
+            }
+        }
+    }
+
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bc1_four_color_palette() {
+        // Red and blue endpoints, pixels cycling through the palette.
+        let bytes = [0x00, 0xf8, 0x1f, 0x00, 0xe4, 0xe4, 0xe4, 0xe4];
+        let palette = [
+            [255, 0, 0, 255],
+            [0, 0, 255, 255],
+            [170, 0, 85, 255],
+            [85, 0, 170, 255],
+        ];
+
+        let block = decode_bc1(&bytes, false);
+        for (i, pixel) in block.iter().enumerate() {
+            assert_eq!(*pixel, palette[i % 4], "pixel {i}");
+        }
+    }
+
+    #[test]
+    fn bc1_three_color_palette_is_transparent() {
+        // Same endpoints swapped, so c0 <= c1 selects the three color palette.
+        let bytes = [0x1f, 0x00, 0x00, 0xf8, 0xe4, 0xe4, 0xe4, 0xe4];
+        let palette = [
+            [0, 0, 255, 255],
+            [255, 0, 0, 255],
+            [128, 0, 128, 255],
+            [0, 0, 0, 0],
+        ];
+
+        let block = decode_bc1(&bytes, false);
+        for (i, pixel) in block.iter().enumerate() {
+            assert_eq!(*pixel, palette[i % 4], "pixel {i}");
+        }
+
+        // BC3 color ignores the endpoint order.
+        let block = decode_bc1(&bytes, true);
+        assert_eq!(block[2], [85, 0, 170, 255]);
+        assert_eq!(block[3], [170, 0, 85, 255]);
+    }
+
+    #[test]
+    fn bc4_palettes() {
+        // Indices 0 to 7, then 7 down to 0.
+        let indices = [0x88, 0xc6, 0xfa, 0x77, 0x39, 0x05];
+        let decode = |a0, a1| {
+            let mut bytes = [a0, a1, 0, 0, 0, 0, 0, 0];
+            bytes[2..].copy_from_slice(&indices);
+            decode_bc4(&bytes)
+        };
+
+        let eight = [200, 60, 180, 160, 140, 120, 100, 80];
+        let block = decode(200, 60);
+        assert_eq!(block[..8], eight);
+        assert!(block[8..].iter().eq(eight.iter().rev()));
+
+        let six = [60, 200, 88, 116, 144, 172, 0, 255];
+        let block = decode(60, 200);
+        assert_eq!(block[..8], six);
+        assert!(block[8..].iter().eq(six.iter().rev()));
+    }
+
+    #[test]
+    fn bc7_partition_tables_are_consistent() {
+        for partition in 0..64 {
+            let subset_2 = |pixel: u8| (BC7_PARTITIONS_2[partition] >> pixel) & 1;
+            let anchor = BC7_ANCHORS_2[partition];
+            assert_eq!(subset_2(0), 0);
+            assert_eq!(subset_2(anchor), 1, "partition {partition}");
+
+            let subset_3 = |pixel: u8| (BC7_PARTITIONS_3[partition] >> (2 * pixel)) & 3;
+            let [second, third] = BC7_ANCHORS_3[partition];
+            assert_eq!(subset_3(0), 0);
+            assert_eq!(subset_3(second), 1, "partition {partition}");
+            assert_eq!(subset_3(third), 2, "partition {partition}");
+        }
+    }
+
+    #[test]
+    fn bc7_decodes_every_mode() {
+        // Blocks are little-endian 128-bit words, pixels are RGBA from the most significant byte.
+        // Modes 1, 3 and 7 use partitions 19, 34 and 52, modes 4 and 5 rotate a channel into
+        // alpha and mode 4 swaps its index sets.
+        #[rustfmt::skip]
+        let blocks: [(u128, [u32; 16]); 8] = [
+        // Mode 0.
+        (
+            0xc2a7af9eab79b0056dd177d2c700d841,
+            [
+                0x6b7cb4ff, 0x6b7cb4ff, 0x41da7fff, 0x5de8a8ff,
+                0x9a888aff, 0xb08e77ff, 0x41da7fff, 0x16c43eff,
+                0xc69463ff, 0x6da725ff, 0x318484ff, 0x5de8a8ff,
+                0x48925fff, 0x6da725ff, 0x84b500ff, 0x61a038ff,
+            ],
+        ),
+        // Mode 1.
+        (
+            0xc2b9546e0f0220f3edb7f1d5cbf1514e,
+            [
+                0x4454b5ff, 0xffff3eff, 0xf0eb36ff, 0xcbb722ff,
+                0x4454b5ff, 0x28346fff, 0xe9e132ff, 0xe9e132ff,
+                0x28346fff, 0x374493ff, 0x222c5eff, 0xe1d52eff,
+                0x303c82ff, 0x222c5eff, 0x4454b5ff, 0x1b244dff,
+            ],
+        ),
+        // Mode 2.
+        (
+            0xe638bca46bd70d89987fc91e855cde04,
+            [
+                0x7b9463ff, 0x91cc31ff, 0x3959c6ff, 0x5aff73ff,
+                0x91cc31ff, 0x9ce718ff, 0x4aae9cff, 0x4aae9cff,
+                0x7b9463ff, 0x394a8cff, 0x8281a2ff, 0x5aff73ff,
+                0x394a8cff, 0xa59cadff, 0x394a8cff, 0x8281a2ff,
+            ],
+        ),
+        // Mode 3.
+        (
+            0x38b1f9a35c2855ba8f0f9d8c7f51c628,
+            [
+                0xe3edddff, 0xffe151ff, 0xb3f1b0ff, 0x31a371ff,
+                0xffe151ff, 0x50f854ff, 0xbbcd5cff, 0x50f854ff,
+                0xb3f1b0ff, 0xffe151ff, 0x50f854ff, 0x75b767ff,
+                0xffe151ff, 0x80f481ff, 0x31a371ff, 0xe3edddff,
+            ],
+        ),
+        // Mode 4.
+        (
+            0x547be3d9f1df80fb4c98cbdb40fe62b0,
+            [
+                0xbe978d4b, 0x30978d4b, 0xbe086b9c, 0x8fffa510,
+                0x5f086b9c, 0x8f978d4b, 0x5f2b7388, 0x5f2b7388,
+                0x8f978d4b, 0x30708361, 0x30086b9c, 0x8f4d7b75,
+                0xbe086b9c, 0xbeffa510, 0xbe4d7b75, 0x30ba9537,
+            ],
+        ),
+        // Mode 5.
+        (
+            0x662e0001e6f57713ca1ebac97bad1f60,
+            [
+                0x87dd583e, 0x87ae928e, 0x87dd583e, 0x87ae928e,
+                0x8797afb5, 0x87ae928e, 0x8797afb5, 0x87ae928e,
+                0xcfae928e, 0xf2ae928e, 0xcf97afb5, 0x87c67565,
+                0xcf97afb5, 0xaadd583e, 0xcf97afb5, 0xaa97afb5,
+            ],
+        ),
+        // Mode 6.
+        (
+            0x71985eb13a8839190e4bd00558bc15c0,
+            [
+                0x7b933e3e, 0x5f8c0f47, 0xa99e8a2f, 0x72912f41,
+                0xa09c7c32, 0xa09c7c32, 0xb3a09d2c, 0x72912f41,
+                0x5f8c0f47, 0xbca2ab29, 0xd8a9da20, 0x84954c3b,
+                0xa09c7c32, 0xa99e8a2f, 0x5f8c0f47, 0x97996d35,
+            ],
+        ),
+        // Mode 7.
+        (
+            0xd6bc0d35ccec83e4083fb0efdb1ef480,
+            [
+                0x9f9a0747, 0xecf2b447, 0xe6e86b5e, 0x5c51098c,
+                0xecf2b447, 0xe6e86b5e, 0xdfdf0404, 0xdfdf0404,
+                0xecf2b447, 0x1c0c0ccf, 0x9f9a0747, 0xe6e86b5e,
+                0x1c0c0ccf, 0x5c51098c, 0xecf2b447, 0xe6e86b5e,
+            ],
+        ),
+        ];
+
+        for (mode, (bits, expected)) in blocks.into_iter().enumerate() {
+            let block = decode_bc7(&bits.to_le_bytes());
+            let pixels = block.map(u32::from_be_bytes);
+            assert_eq!(pixels, expected, "mode {mode}");
+        }
+    }
+
+    #[test]
+    fn bc7_reserved_mode_is_transparent_black() {
+        assert_eq!(decode_bc7(&[0; 16]), [[0; 4]; 16]);
+    }
+}
@@ -0,0 +1,300 @@
+use glam::{Mat4, Vec3};
+
+/// Half-line starting at `origin`.
+///
+/// Distances returned by intersection tests are expressed in multiples of `direction`, so they
+/// are world units only when it is normalized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    /// Ray going from `start` through `end`, reaching it at `t = 1`.
+    pub fn from_segment(start: Vec3, end: Vec3) -> Self {
+        Self::new(start, end - start)
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    /// Transform the ray, keeping distances along it unchanged.
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        Self {
+            origin: matrix.transform_point3(self.origin),
+            direction: matrix.transform_vector3(self.direction),
+        }
+    }
+
+    /// Möller–Trumbore ray/triangle test, returning the distance to the hit. Both faces count.
+    pub fn intersect_triangle(&self, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+        let (ab, ac) = (b - a, c - a);
+        let p = self.direction.cross(ac);
+        let det = ab.dot(p);
+        if det.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        let inv_det = det.recip();
+        let to_origin = self.origin - a;
+
+        let u = to_origin.dot(p) * inv_det;
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+
+        let q = to_origin.cross(ab);
+        let v = self.direction.dot(q) * inv_det;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+
+        let t = ac.dot(q) * inv_det;
+        (t >= 0.).then_some(t)
+    }
+}
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl Aabb {
+    /// Box containing nothing, which any point or box grows it to.
+    pub const EMPTY: Self = Self {
+        min: Vec3::INFINITY,
+        max: Vec3::NEG_INFINITY,
+    };
+
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut res = Self::EMPTY;
+        for p in points {
+            res.grow(p);
+        }
+        res
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.
+    }
+
+    /// Half of the box's size along each axis.
+    pub fn extents(&self) -> Vec3 {
+        (self.max - self.min) / 2.
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Extend the box to contain `p`.
+    pub fn grow(&mut self, p: Vec3) {
+        self.min = self.min.min(p);
+        self.max = self.max.max(p);
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Smallest axis-aligned box containing this box once transformed.
+    pub fn transform(&self, matrix: &Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+
+        // Arvo's method: project the extents onto each axis of the transformed space.
+        let center = matrix.transform_point3(self.center());
+        let extents = self.extents();
+        let half = Vec3::new(
+            matrix.row(0).truncate().abs().dot(extents),
+            matrix.row(1).truncate().abs().dot(extents),
+            matrix.row(2).truncate().abs().dot(extents),
+        );
+
+        Self {
+            min: center - half,
+            max: center + half,
+        }
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        p.cmpge(self.min).all() && p.cmple(self.max).all()
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        other.min.cmpge(self.min).all() && other.max.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    /// Distance along the ray to the first point inside the box, 0 when starting inside.
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let inv_dir = ray.direction.recip();
+        let t0 = (self.min - ray.origin) * inv_dir;
+        let t1 = (self.max - ray.origin) * inv_dir;
+
+        // NaNs from zero direction components on the slab boundary are ignored by min/max.
+        let t_near = t0.min(t1).max_element().max(0.);
+        let t_far = t0.max(t1).min_element();
+
+        (t_near <= t_far).then_some(t_near)
+    }
+
+    /// Fraction of the way from `start` to `end` at which the segment enters the box.
+    pub fn intersect_segment(&self, start: Vec3, end: Vec3) -> Option<f32> {
+        self.intersect_ray(&Ray::from_segment(start, end))
+            .filter(|&t| t <= 1.)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Sphere passing through the box's corners.
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        if aabb.is_empty() {
+            return Self::new(Vec3::ZERO, 0.);
+        }
+
+        Self::new(aabb.center(), aabb.extents().length())
+    }
+
+    /// Sphere centered on the points' bounding box, just large enough to hold them.
+    pub fn from_points(points: impl Iterator<Item = Vec3> + Clone) -> Self {
+        let aabb = Aabb::from_points(points.clone());
+        if aabb.is_empty() {
+            return Self::new(Vec3::ZERO, 0.);
+        }
+
+        let center = aabb.center();
+        let radius = points.map(|p| p.distance(center)).fold(0., f32::max);
+
+        Self::new(center, radius)
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.center - self.radius, self.center + self.radius)
+    }
+
+    pub fn union(&self, other: &BoundingSphere) -> BoundingSphere {
+        let offset = other.center - self.center;
+        let distance = offset.length();
+
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+
+        let radius = (distance + self.radius + other.radius) / 2.;
+        let center = self.center + offset * ((radius - self.radius) / distance);
+
+        Self::new(center, radius)
+    }
+
+    /// Sphere containing this one once transformed, scaled by the largest axis scale.
+    pub fn transform(&self, matrix: &Mat4) -> BoundingSphere {
+        let scale = matrix
+            .x_axis
+            .truncate()
+            .length()
+            .max(matrix.y_axis.truncate().length())
+            .max(matrix.z_axis.truncate().length());
+
+        Self::new(matrix.transform_point3(self.center), self.radius * scale)
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        self.center.distance_squared(p) <= self.radius * self.radius
+    }
+
+    pub fn contains(&self, other: &BoundingSphere) -> bool {
+        self.center.distance(other.center) + other.radius <= self.radius
+    }
+
+    pub fn intersects(&self, other: &BoundingSphere) -> bool {
+        let radii = self.radius + other.radius;
+        self.center.distance_squared(other.center) <= radii * radii
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let closest = self.center.clamp(aabb.min, aabb.max);
+        self.contains_point(closest)
+    }
+
+    /// Distance along the ray to the first point inside the sphere, 0 when starting inside.
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let to_center = self.center - ray.origin;
+        let a = ray.direction.length_squared();
+        let b = to_center.dot(ray.direction);
+        let c = to_center.length_squared() - self.radius * self.radius;
+
+        if c <= 0. {
+            return Some(0.);
+        }
+
+        let discriminant = b * b - a * c;
+        if discriminant < 0. || b < 0. || a == 0. {
+            return None;
+        }
+
+        Some((b - discriminant.sqrt()) / a)
+    }
+
+    /// Fraction of the way from `start` to `end` at which the segment enters the sphere.
+    pub fn intersect_segment(&self, start: Vec3, end: Vec3) -> Option<f32> {
+        self.intersect_ray(&Ray::from_segment(start, end))
+            .filter(|&t| t <= 1.)
+    }
+}
@@ -2,7 +2,7 @@ use std::ffi::c_void;
 
 use gl::types::GLuint;
 
-use crate::{prelude::dogl, AsSlice};
+use crate::prelude::dogl;
 
 use super::handle::{BufferUsage, GLHandle};
 
@@ -18,6 +18,45 @@ pub struct GLBuffer<T> {
 
 impl<T> GLBuffer<T> {
     pub fn new(data: &[T]) -> Self {
+        Self::with_usage_hint(data, gl::STATIC_DRAW)
+    }
+
+    /// Create a buffer whose contents are expected to be rewritten often.
+    pub fn new_dynamic(data: &[T]) -> Self {
+        Self::with_usage_hint(data, gl::DYNAMIC_DRAW)
+    }
+
+    /// Create a buffer from raw bytes holding tightly packed `T`s.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        assert_eq!(
+            bytes.len() % std::mem::size_of::<T>(),
+            0,
+            "Byte length is not a multiple of the element size"
+        );
+
+        Self::from_raw(
+            bytes.as_ptr() as *const c_void,
+            bytes.len(),
+            bytes.len() / std::mem::size_of::<T>(),
+            gl::STATIC_DRAW,
+        )
+    }
+
+    fn with_usage_hint(data: &[T], hint: gl::types::GLenum) -> Self {
+        Self::from_raw(
+            data.as_ptr() as *const c_void,
+            std::mem::size_of_val(data),
+            data.len(),
+            hint,
+        )
+    }
+
+    fn from_raw(
+        data: *const c_void,
+        byte_size: usize,
+        size: usize,
+        hint: gl::types::GLenum,
+    ) -> Self {
         fn create_buffer_handle() -> GLHandle {
             let mut handle: GLuint = 0;
             unsafe {
@@ -27,16 +66,13 @@ impl<T> GLBuffer<T> {
         }
 
         unsafe {
-            let data = data.as_u8_slice();
             let handle = create_buffer_handle();
-            let size = data.len();
 
             dogl!(gl::NamedBufferData(
                 handle.get(),
-                // Byte size
-                (size * std::mem::size_of::<T>()) as isize,
-                data.as_ptr() as *const c_void,
-                gl::STATIC_DRAW,
+                byte_size as isize,
+                data,
+                hint,
             ));
 
             Self {
@@ -59,6 +95,26 @@ impl<T> GLBuffer<T> {
         self.size * std::mem::size_of::<T>()
     }
 
+    /// Overwrite elements starting at `offset` (in elements).
+    pub fn update(&self, offset: usize, data: &[T]) {
+        assert!(
+            offset + data.len() <= self.size,
+            "Buffer update out of bounds ({}..{} > {})",
+            offset,
+            offset + data.len(),
+            self.size
+        );
+
+        unsafe {
+            dogl!(gl::NamedBufferSubData(
+                self.handle.get(),
+                (offset * std::mem::size_of::<T>()) as isize,
+                std::mem::size_of_val(data) as isize,
+                data.as_ptr() as *const c_void,
+            ));
+        }
+    }
+
     pub fn bind(&self, usage: BufferUsage) {
         unsafe {
             dogl!(gl::BindBuffer(usage.into(), self.handle.get()));
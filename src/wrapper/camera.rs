@@ -1,14 +1,114 @@
 use glam::Vec4;
 
+use super::bounds::{Aabb, BoundingSphere, Ray};
+
+/// Camera frustum as inward-facing planes.
 pub struct Frustum {
-    near_normal: glam::Vec3,
-    // No far plane (zFar is +inf)
-    top_normal: glam::Vec3,
-    bottom_normal: glam::Vec3,
-    right_normal: glam::Vec3,
-    left_normal: glam::Vec3,
+    /// Normalized planes, with `plane.xyz().dot(p) + plane.w >= 0` for points inside. Planes at
+    /// infinity (such as the far plane of an infinite perspective) are left out.
+    planes: Vec<Vec4>,
+}
+
+impl Frustum {
+    /// Extract the planes of a reverse-Z, zero-to-one clip space.
+    pub fn from_view_proj(view_proj: &glam::Mat4) -> Self {
+        let (r0, r1, r2, r3) = (
+            view_proj.row(0),
+            view_proj.row(1),
+            view_proj.row(2),
+            view_proj.row(3),
+        );
+
+        let planes = [
+            r3 + r0, // Left
+            r3 - r0, // Right
+            r3 + r1, // Bottom
+            r3 - r1, // Top
+            r3 - r2, // Near (z <= w)
+            r2,      // Far (z >= 0)
+        ]
+        .into_iter()
+        .filter_map(|plane| {
+            let length = plane.truncate().length();
+            (length > f32::EPSILON).then(|| plane / length)
+        })
+        .collect();
+
+        Self { planes }
+    }
+
+    fn distance(plane: &Vec4, p: glam::Vec3) -> f32 {
+        plane.truncate().dot(p) + plane.w
+    }
+
+    pub fn contains_point(&self, p: glam::Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| Frustum::distance(plane, p) >= 0.)
+    }
+
+    /// Conservative test: may accept spheres lying just outside a frustum corner.
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| Frustum::distance(plane, sphere.center) >= -sphere.radius)
+    }
+
+    /// Conservative test: may accept boxes lying just outside a frustum corner.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner furthest along the plane normal.
+            let positive =
+                glam::Vec3::select(plane.truncate().cmpge(glam::Vec3::ZERO), aabb.max, aabb.min);
+            Frustum::distance(plane, positive) >= 0.
+        })
+    }
 }
 
+/// Rectangle of the window a camera renders to, in pixels from the top-left corner as reported
+/// by cursor events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Viewport covering a whole window or texture.
+    pub fn full(width: u32, height: u32) -> Self {
+        Self::new(0, 0, width, height)
+    }
+
+    /// Width over height, not finite for empty viewports.
+    pub fn ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// Whether the viewport covers no pixels, as when a window is minimized.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Normalized device coordinates of a pixel, with y pointing up.
+    pub fn to_ndc(&self, pixel: glam::Vec2) -> glam::Vec2 {
+        let relative = (pixel - glam::vec2(self.x as f32, self.y as f32))
+            / glam::vec2(self.width as f32, self.height as f32);
+        glam::vec2(relative.x * 2. - 1., 1. - relative.y * 2.)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Camera {
     pub projection: glam::Mat4,
     pub view: glam::Mat4,
@@ -18,7 +118,7 @@ pub struct Camera {
 impl Default for Camera {
     fn default() -> Self {
         let projection = Self::perspective(70f32.to_radians(), 16. / 9., 1e-3);
-        let view = glam::Mat4::look_to_rh(glam::vec3(2., 2., 2.), glam::Vec3::ZERO, glam::Vec3::Y);
+        let view = glam::Mat4::look_at_rh(glam::vec3(2., 2., 2.), glam::Vec3::ZERO, glam::Vec3::Y);
         Self {
             projection,
             view,
@@ -34,6 +134,7 @@ macro_rules! get {
 }
 
 impl Camera {
+    /// Infinite reverse-Z perspective projection.
     #[rustfmt::skip]
     pub fn perspective(fov_y: f32, ratio: f32, z_near: f32) -> glam::Mat4 {
         let f = 1. / (fov_y / 2.).tan();
@@ -47,8 +148,63 @@ impl Camera {
         )
     }
 
+    /// Infinite reverse-Z perspective projection with an asymmetric frustum, whose extents are
+    /// given on the near plane.
+    #[rustfmt::skip]
+    pub fn perspective_off_axis(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        z_near: f32,
+    ) -> glam::Mat4 {
+        let (width, height) = (right - left, top - bottom);
+        glam::Mat4::from_cols_array(
+            &[
+                2. * z_near / width    , 0.                      ,   0.  ,  0.,
+                0.                     , 2. * z_near / height    ,   0.  ,  0.,
+                (right + left) / width , (top + bottom) / height ,   0.  , -1.,
+                0.                     , 0.                      , z_near,  0.
+            ]
+        )
+    }
+
+    /// Reverse-Z orthographic projection of a `width` by `height` box centered on the view axis.
+    pub fn orthographic(width: f32, height: f32, z_near: f32, z_far: f32) -> glam::Mat4 {
+        Camera::orthographic_off_axis(
+            -width / 2.,
+            width / 2.,
+            -height / 2.,
+            height / 2.,
+            z_near,
+            z_far,
+        )
+    }
+
+    /// Reverse-Z orthographic projection of an arbitrary box, mapping `z_near` to a depth of 1
+    /// and `z_far` to 0.
+    #[rustfmt::skip]
+    pub fn orthographic_off_axis(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        z_near: f32,
+        z_far: f32,
+    ) -> glam::Mat4 {
+        let (width, height, depth) = (right - left, top - bottom, z_far - z_near);
+        glam::Mat4::from_cols_array(
+            &[
+                2. / width               , 0.                       , 0.            , 0.,
+                0.                       , 2. / height              , 0.            , 0.,
+                0.                       , 0.                       , 1. / depth    , 0.,
+                -(right + left) / width  , -(top + bottom) / height , z_far / depth , 1.
+            ]
+        )
+    }
+
     pub fn new() -> Self {
-        Self {
+        let mut camera = Self {
             projection: Camera::perspective(60f32.to_radians(), 16. / 9., 1e-3),
             view: glam::f32::Mat4::look_at_rh(
                 glam::vec3(2., 2., 2.),
@@ -57,7 +213,9 @@ impl Camera {
             ),
 
             view_proj: glam::Mat4::from_cols_array(&[0.; 16]).to_owned(),
-        }
+        };
+        camera.update();
+        camera
     }
 
     fn update(&mut self) {
@@ -74,24 +232,47 @@ impl Camera {
         self.update();
     }
 
+    /// Change the vertical field of view, keeping the aspect ratio and any off-axis shift.
+    ///
+    /// Orthographic projections have no field of view and are left untouched.
     pub fn set_fov(&mut self, fov: f32) {
-        self.set_proj(&Camera::perspective(
-            fov,
-            self.ratio(),
-            Camera::extract_near(&self.projection),
-        ));
+        if self.is_orthographic() {
+            return;
+        }
+
+        let scale = (1. / (fov / 2.).tan()) / get!(self.projection, 1, 1);
+        let mut projection = self.projection;
+        projection.x_axis.x *= scale;
+        projection.y_axis.y *= scale;
+        self.set_proj(&projection);
     }
 
+    /// Change the aspect ratio by adjusting the horizontal extent, keeping the vertical one.
     pub fn set_ratio(&mut self, ratio: f32) {
-        self.set_proj(&Camera::perspective(
-            self.fov(),
-            ratio,
-            Camera::extract_near(&self.projection),
-        ))
+        let mut projection = self.projection;
+        projection.x_axis.x = get!(projection, 1, 1) / ratio;
+        self.set_proj(&projection);
     }
 
     fn extract_near(projection: &glam::Mat4) -> f32 {
-        get!(projection, 3, 2)
+        if get!(projection, 3, 3) == 1. {
+            (get!(projection, 3, 2) - 1.) / get!(projection, 2, 2)
+        } else {
+            get!(projection, 3, 2)
+        }
+    }
+
+    pub fn near(&self) -> f32 {
+        Camera::extract_near(&self.projection)
+    }
+
+    /// Far plane distance, infinite for perspective projections.
+    pub fn far(&self) -> f32 {
+        if self.is_orthographic() {
+            get!(self.projection, 3, 2) / get!(self.projection, 2, 2)
+        } else {
+            f32::INFINITY
+        }
     }
 
     pub fn fov(&self) -> f32 {
@@ -103,8 +284,7 @@ impl Camera {
     }
 
     pub fn ratio(&self) -> f32 {
-        let f: f32 = get!(self.projection, 1, 1);
-        (1.0 / (get!(self.projection, 0, 0)) / f).abs()
+        (get!(self.projection, 1, 1) / get!(self.projection, 0, 0)).abs()
     }
 
     pub fn position(&self) -> glam::Vec3 {
@@ -151,23 +331,34 @@ impl Camera {
         get!(self.projection, 3, 3) == 1.
     }
 
-    pub fn build_frustum(&self) -> Frustum {
-        let camera_up = self.up();
-        let camera_right = self.right();
-        let camera_forward = self.forward();
-
-        let half_fov = self.fov() * 0.5;
-        let half_fov_v = (half_fov.tan() * self.ratio()).atan();
-
-        let (c, s) = (half_fov.cos(), half_fov.sin());
-        let (c_v, s_v) = (half_fov_v.cos(), half_fov_v.sin());
-
-        Frustum {
-            near_normal: camera_forward,
-            top_normal: camera_forward * s - camera_up * c,
-            bottom_normal: camera_forward * s + camera_up * c,
-            right_normal: camera_forward * s_v - camera_right * c_v,
-            left_normal: camera_forward * s_v + camera_right * c_v,
+    /// Projected diameter of a world-space sphere, as a fraction of the viewport height.
+    pub fn projected_size(&self, sphere: &BoundingSphere) -> f32 {
+        let scale = get!(self.projection, 1, 1);
+
+        if self.is_orthographic() {
+            sphere.radius * scale
+        } else {
+            let distance = self.position().distance(sphere.center);
+            // Treat the camera being inside the sphere as covering the whole screen.
+            sphere.radius * scale / distance.max(sphere.radius)
         }
     }
+
+    pub fn build_frustum(&self) -> Frustum {
+        Frustum::from_view_proj(&self.view_proj)
+    }
+
+    /// World-space ray starting on the near plane and going through `pixel`, with a normalized
+    /// direction.
+    pub fn screen_ray(&self, pixel: glam::Vec2, viewport: &Viewport) -> Ray {
+        let ndc = viewport.to_ndc(pixel);
+        let inverse = self.view_proj.inverse();
+
+        // Reverse-Z puts the near plane at a depth of 1. The far plane of an infinite perspective
+        // sits at 0 and unprojects to infinity, so aim at a point halfway in depth instead.
+        let near = inverse.project_point3(ndc.extend(1.));
+        let through = inverse.project_point3(ndc.extend(0.5));
+
+        Ray::new(near, (through - near).normalize())
+    }
 }
@@ -0,0 +1,436 @@
+//! Keyframed camera animation.
+//!
+//! Paths are stored as plain text, one keyframe per line:
+//!
+//! ```text
+//! camera_path 1
+//! interpolation catmull_rom
+//! key <time> <position xyz> <orientation xyzw> <fov> <easing> <in handle xyz> <out handle xyz>
+//! ```
+//!
+//! Floats are written with their shortest round-tripping representation, so a saved path
+//! replays exactly as authored.
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use glam::{Quat, Vec3};
+use glfw::WindowEvent;
+
+use super::{camera::Camera, controller::CameraController};
+
+pub const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum CameraPathError {
+    Io(std::io::Error),
+    Parse { line: usize, message: &'static str },
+}
+
+impl Display for CameraPathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CameraPathError::Io(err) => write!(f, "I/O error: {err}"),
+            CameraPathError::Parse { line, message } => {
+                write!(f, "invalid camera path at line {line}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CameraPathError {}
+
+impl From<std::io::Error> for CameraPathError {
+    fn from(value: std::io::Error) -> Self {
+        CameraPathError::Io(value)
+    }
+}
+
+/// Remapping of the progress between two keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2. - t),
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Easing::Linear => "linear",
+            Easing::EaseIn => "ease_in",
+            Easing::EaseOut => "ease_out",
+            Easing::EaseInOut => "ease_in_out",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "linear" => Easing::Linear,
+            "ease_in" => Easing::EaseIn,
+            "ease_out" => Easing::EaseOut,
+            "ease_in_out" => Easing::EaseInOut,
+            _ => return None,
+        })
+    }
+}
+
+/// Curve followed by the camera position between keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Passes through every keyframe, with tangents derived from the neighbouring ones.
+    #[default]
+    CatmullRom,
+    /// Cubic Bezier shaped by each keyframe's handles.
+    Bezier,
+}
+
+impl Interpolation {
+    fn name(&self) -> &'static str {
+        match self {
+            Interpolation::CatmullRom => "catmull_rom",
+            Interpolation::Bezier => "bezier",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "catmull_rom" => Interpolation::CatmullRom,
+            "bezier" => Interpolation::Bezier,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    /// Seconds since the start of the path.
+    pub time: f32,
+    pub position: Vec3,
+    pub orientation: Quat,
+    /// Vertical field of view, in radians.
+    pub fov: f32,
+    /// Easing of the segment leading to the next keyframe.
+    pub easing: Easing,
+    /// Bezier control points, relative to `position`. Unused by Catmull-Rom paths.
+    pub in_handle: Vec3,
+    pub out_handle: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f32, position: Vec3, orientation: Quat, fov: f32) -> Self {
+        Self {
+            time,
+            position,
+            orientation,
+            fov,
+            easing: Easing::Linear,
+            in_handle: Vec3::ZERO,
+            out_handle: Vec3::ZERO,
+        }
+    }
+
+    /// Keyframe at `position` facing `target`.
+    pub fn looking_at(time: f32, position: Vec3, target: Vec3, fov: f32) -> Self {
+        let view = glam::Mat4::look_at_rh(position, target, Vec3::Y);
+        let (_, orientation, _) = view.inverse().to_scale_rotation_translation();
+        Self::new(time, position, orientation, fov)
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_handles(mut self, in_handle: Vec3, out_handle: Vec3) -> Self {
+        self.in_handle = in_handle;
+        self.out_handle = out_handle;
+        self
+    }
+}
+
+/// Camera state at a given time along a path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraPose {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub fov: f32,
+}
+
+impl CameraPose {
+    pub fn view(&self) -> glam::Mat4 {
+        glam::Mat4::from_rotation_translation(self.orientation, self.position).inverse()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CameraPath {
+    pub interpolation: Interpolation,
+    /// Sorted by time.
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            interpolation,
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Insert a keyframe, keeping them ordered by time.
+    pub fn add_keyframe(&mut self, keyframe: Keyframe) {
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0., |k| k.time)
+    }
+
+    /// Pose at `time`, holding the first and last keyframes outside of the path's range.
+    ///
+    /// Panics if the path has no keyframes.
+    pub fn sample(&self, time: f32) -> CameraPose {
+        assert!(!self.keyframes.is_empty(), "Sampling an empty camera path");
+
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return Self::pose(&self.keyframes[0]);
+        }
+        if next == self.keyframes.len() {
+            return Self::pose(&self.keyframes[next - 1]);
+        }
+
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let t = a.easing.apply((time - a.time) / (b.time - a.time));
+
+        let position = match self.interpolation {
+            Interpolation::CatmullRom => {
+                let before = &self.keyframes[(next - 1).saturating_sub(1)];
+                let after = &self.keyframes[(next + 1).min(self.keyframes.len() - 1)];
+                catmull_rom(before.position, a.position, b.position, after.position, t)
+            }
+            Interpolation::Bezier => bezier(
+                a.position,
+                a.position + a.out_handle,
+                b.position + b.in_handle,
+                b.position,
+                t,
+            ),
+        };
+
+        CameraPose {
+            position,
+            orientation: a.orientation.slerp(b.orientation, t),
+            fov: a.fov + (b.fov - a.fov) * t,
+        }
+    }
+
+    /// Move `camera` to the path's pose at `time`.
+    pub fn apply(&self, camera: &mut Camera, time: f32) {
+        let pose = self.sample(time);
+        camera.set_view(&pose.view());
+        camera.set_fov(pose.fov);
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CameraPathError> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CameraPathError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    fn pose(keyframe: &Keyframe) -> CameraPose {
+        CameraPose {
+            position: keyframe.position,
+            orientation: keyframe.orientation,
+            fov: keyframe.fov,
+        }
+    }
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+    0.5 * (2. * p1
+        + (p2 - p0) * t
+        + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+        + (3. * p1 - p0 - 3. * p2 + p3) * t3)
+}
+
+fn bezier(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let u = 1. - t;
+    p0 * (u * u * u) + p1 * (3. * u * u * t) + p2 * (3. * u * t * t) + p3 * (t * t * t)
+}
+
+impl Display for CameraPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "camera_path {VERSION}")?;
+        writeln!(f, "interpolation {}", self.interpolation.name())?;
+
+        for k in &self.keyframes {
+            let (p, q, i, o) = (k.position, k.orientation, k.in_handle, k.out_handle);
+            writeln!(
+                f,
+                "key {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+                k.time,
+                p.x,
+                p.y,
+                p.z,
+                q.x,
+                q.y,
+                q.z,
+                q.w,
+                k.fov,
+                k.easing.name(),
+                i.x,
+                i.y,
+                i.z,
+                o.x,
+                o.y,
+                o.z,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for CameraPath {
+    type Err = CameraPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let error = |line, message| CameraPathError::Parse { line, message };
+
+        let (line, header) = lines.next().ok_or(error(1, "missing header"))?;
+        match header.split_whitespace().collect::<Vec<_>>()[..] {
+            ["camera_path", version] if version.parse() == Ok(VERSION) => {}
+            ["camera_path", _] => return Err(error(line, "unsupported version")),
+            _ => return Err(error(line, "missing header")),
+        }
+
+        let mut path = CameraPath::default();
+
+        for (line, content) in lines {
+            let mut words = content.split_whitespace();
+
+            match words.next() {
+                Some("interpolation") => {
+                    path.interpolation = words
+                        .next()
+                        .and_then(Interpolation::from_name)
+                        .ok_or(error(line, "unknown interpolation"))?;
+                }
+                Some("key") => {
+                    let fields: Vec<_> = words.collect();
+                    if fields.len() != 16 {
+                        return Err(error(line, "expected 16 fields"));
+                    }
+
+                    let float = |i: usize| -> Result<f32, CameraPathError> {
+                        fields[i]
+                            .parse()
+                            .map_err(|_| error(line, "expected a number"))
+                    };
+                    let vec3 = |i: usize| -> Result<Vec3, CameraPathError> {
+                        Ok(Vec3::new(float(i)?, float(i + 1)?, float(i + 2)?))
+                    };
+
+                    let time = float(0)?;
+                    let position = vec3(1)?;
+                    let orientation = Quat::from_xyzw(float(4)?, float(5)?, float(6)?, float(7)?);
+                    let fov = float(8)?;
+                    let easing =
+                        Easing::from_name(fields[9]).ok_or(error(line, "unknown easing"))?;
+                    let in_handle = vec3(10)?;
+                    let out_handle = vec3(13)?;
+
+                    if !orientation.is_finite() || orientation.length_squared() == 0. {
+                        return Err(error(line, "invalid orientation"));
+                    }
+                    // Keep stored unit quaternions bit-exact, only fixing hand-edited ones.
+                    let orientation = if orientation.is_normalized() {
+                        orientation
+                    } else {
+                        orientation.normalize()
+                    };
+
+                    path.add_keyframe(Keyframe {
+                        time,
+                        position,
+                        orientation,
+                        fov,
+                        easing,
+                        in_handle,
+                        out_handle,
+                    });
+                }
+                _ => return Err(error(line, "unknown directive")),
+            }
+        }
+
+        Ok(path)
+    }
+}
+
+/// Plays a path back in real time, for use alongside the interactive controllers.
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    pub time: f32,
+    pub looping: bool,
+}
+
+impl CameraPathPlayer {
+    pub fn new(path: CameraPath) -> Self {
+        Self {
+            path,
+            time: 0.,
+            looping: false,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.time >= self.path.duration()
+    }
+}
+
+impl CameraController for CameraPathPlayer {
+    fn handle_event(&mut self, _event: &WindowEvent) {}
+
+    fn update(&mut self, camera: &mut Camera, delta: f32) {
+        if self.path.keyframes().is_empty() {
+            return;
+        }
+
+        self.time += delta;
+        let duration = self.path.duration();
+        if self.looping && duration > 0. {
+            self.time %= duration;
+        }
+
+        self.path.apply(camera, self.time);
+    }
+}
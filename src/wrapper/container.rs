@@ -0,0 +1,261 @@
+//! Loading of KTX2 and DDS texture containers, which carry GPU formats and their mip chains.
+
+use glam::UVec2;
+
+use super::{
+    image::{ColorSpace, TextureError},
+    texture::{mip_size, ImageFormat, Texture, TextureData},
+};
+
+const KTX2_IDENTIFIER: [u8; 12] = *b"\xabKTX 20\xbb\r\n\x1a\n";
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+/// Whether `bytes` start like a KTX2 or DDS file.
+pub(crate) fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(&KTX2_IDENTIFIER) || bytes.starts_with(&DDS_MAGIC)
+}
+
+fn invalid(reason: &str) -> TextureError {
+    TextureError::Decode(reason.to_owned())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, TextureError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| invalid("truncated header"))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, TextureError> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| invalid("truncated header"))
+}
+
+fn check_size(size: UVec2, levels: u32) -> Result<(), TextureError> {
+    if size.x == 0 || size.y == 0 {
+        return Err(invalid("image has no pixels"));
+    }
+    if levels > Texture::mip_levels(size) {
+        return Err(invalid("more mip levels than the image size allows"));
+    }
+    Ok(())
+}
+
+/// Pick the sRGB or linear variant of a format, for containers that do not say.
+fn with_color_space(format: ImageFormat, color_space: ColorSpace) -> ImageFormat {
+    match (format, color_space) {
+        (ImageFormat::Bc1RgbaUnorm, ColorSpace::Srgb) => ImageFormat::Bc1RgbaSRgb,
+        (ImageFormat::Bc3RgbaUnorm, ColorSpace::Srgb) => ImageFormat::Bc3RgbaSRgb,
+        (ImageFormat::Bc7RgbaUnorm, ColorSpace::Srgb) => ImageFormat::Bc7RgbaSRgb,
+        (ImageFormat::Rgba8Unorm, ColorSpace::Srgb) => ImageFormat::Rgba8SRgb,
+        (format, _) => format,
+    }
+}
+
+fn ktx2_format(vk_format: u32) -> Option<ImageFormat> {
+    Some(match vk_format {
+        9 => ImageFormat::R8Unorm,
+        13 => ImageFormat::R8Uint,
+        16 => ImageFormat::Rg8Unorm,
+        37 => ImageFormat::Rgba8Unorm,
+        41 => ImageFormat::Rgba8Uint,
+        43 => ImageFormat::Rgba8SRgb,
+        64 => ImageFormat::Rgb10A2Unorm,
+        76 => ImageFormat::R16Float,
+        83 => ImageFormat::Rg16Float,
+        91 => ImageFormat::Rgba16Unorm,
+        97 => ImageFormat::Rgba16Float,
+        98 => ImageFormat::R32Uint,
+        99 => ImageFormat::R32Sint,
+        100 => ImageFormat::R32Float,
+        103 => ImageFormat::Rg32Float,
+        107 => ImageFormat::Rgba32Uint,
+        109 => ImageFormat::Rgba32Float,
+        122 => ImageFormat::R11G11B10Float,
+        // The RGB and RGBA variants of BC1 share a layout.
+        131 | 133 => ImageFormat::Bc1RgbaUnorm,
+        132 | 134 => ImageFormat::Bc1RgbaSRgb,
+        137 => ImageFormat::Bc3RgbaUnorm,
+        138 => ImageFormat::Bc3RgbaSRgb,
+        139 => ImageFormat::Bc4RUnorm,
+        141 => ImageFormat::Bc5RgUnorm,
+        145 => ImageFormat::Bc7RgbaUnorm,
+        146 => ImageFormat::Bc7RgbaSRgb,
+        _ => return None,
+    })
+}
+
+fn dxgi_format(dxgi_format: u32) -> Option<ImageFormat> {
+    Some(match dxgi_format {
+        2 => ImageFormat::Rgba32Float,
+        3 => ImageFormat::Rgba32Uint,
+        10 => ImageFormat::Rgba16Float,
+        11 => ImageFormat::Rgba16Unorm,
+        16 => ImageFormat::Rg32Float,
+        24 => ImageFormat::Rgb10A2Unorm,
+        26 => ImageFormat::R11G11B10Float,
+        28 => ImageFormat::Rgba8Unorm,
+        29 => ImageFormat::Rgba8SRgb,
+        30 => ImageFormat::Rgba8Uint,
+        34 => ImageFormat::Rg16Float,
+        41 => ImageFormat::R32Float,
+        42 => ImageFormat::R32Uint,
+        43 => ImageFormat::R32Sint,
+        49 => ImageFormat::Rg8Unorm,
+        54 => ImageFormat::R16Float,
+        61 => ImageFormat::R8Unorm,
+        62 => ImageFormat::R8Uint,
+        71 => ImageFormat::Bc1RgbaUnorm,
+        72 => ImageFormat::Bc1RgbaSRgb,
+        77 => ImageFormat::Bc3RgbaUnorm,
+        78 => ImageFormat::Bc3RgbaSRgb,
+        80 => ImageFormat::Bc4RUnorm,
+        83 => ImageFormat::Bc5RgUnorm,
+        98 => ImageFormat::Bc7RgbaUnorm,
+        99 => ImageFormat::Bc7RgbaSRgb,
+        _ => return None,
+    })
+}
+
+/// Slice the levels of a mip chain stored back to back from `offset`.
+fn read_levels(
+    bytes: &[u8],
+    mut offset: usize,
+    size: UVec2,
+    levels: u32,
+    format: ImageFormat,
+) -> Result<Vec<Vec<u8>>, TextureError> {
+    (0..levels)
+        .map(|level| {
+            let len = format.data_size(mip_size(size, level));
+            let data = bytes
+                .get(offset..offset + len)
+                .ok_or_else(|| invalid("truncated image data"))?;
+            offset += len;
+            Ok(data.to_vec())
+        })
+        .collect()
+}
+
+impl TextureData {
+    /// Load a 2D KTX2 texture, without supercompression.
+    pub fn from_ktx2(bytes: &[u8]) -> Result<TextureData, TextureError> {
+        if !bytes.starts_with(&KTX2_IDENTIFIER) {
+            return Err(invalid("not a KTX2 file"));
+        }
+
+        let vk_format = read_u32(bytes, 12)?;
+        let size = UVec2::new(read_u32(bytes, 20)?, read_u32(bytes, 24)?);
+        let depth = read_u32(bytes, 28)?;
+        let layers = read_u32(bytes, 32)?;
+        let faces = read_u32(bytes, 36)?;
+        // Zero asks the loader to generate the mip chain.
+        let levels = read_u32(bytes, 40)?.max(1);
+        let supercompression = read_u32(bytes, 44)?;
+
+        if depth != 0 || layers != 0 || faces != 1 {
+            return Err(TextureError::Unsupported(
+                "KTX2 textures other than 2D".to_owned(),
+            ));
+        }
+        if supercompression != 0 {
+            return Err(TextureError::Unsupported(format!(
+                "KTX2 supercompression scheme {supercompression}"
+            )));
+        }
+        let format = ktx2_format(vk_format)
+            .ok_or_else(|| TextureError::Unsupported(format!("KTX2 Vulkan format {vk_format}")))?;
+        check_size(size, levels)?;
+
+        // The level index follows the 80 byte header, one entry of offset, length and
+        // uncompressed length per level.
+        let data = (0..levels as usize)
+            .map(|level| {
+                let entry = 80 + level * 24;
+                let offset = read_u64(bytes, entry)? as usize;
+                let len = read_u64(bytes, entry + 8)? as usize;
+
+                if len != format.data_size(mip_size(size, level as u32)) {
+                    return Err(invalid("level size does not match the format"));
+                }
+                offset
+                    .checked_add(len)
+                    .and_then(|end| bytes.get(offset..end))
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| invalid("truncated image data"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(TextureData::with_mips(data, size, format))
+    }
+
+    /// Load a 2D DDS texture. Legacy headers do not say whether colors are sRGB, so
+    /// `color_space` picks the variant for them, while DX10 headers are followed as is.
+    pub fn from_dds(bytes: &[u8], color_space: ColorSpace) -> Result<TextureData, TextureError> {
+        const FOURCC: u32 = 0x4;
+        const RGB: u32 = 0x40;
+        const CUBEMAP: u32 = 0x200;
+        const VOLUME: u32 = 0x20_0000;
+
+        if !bytes.starts_with(&DDS_MAGIC) || read_u32(bytes, 4)? != 124 {
+            return Err(invalid("not a DDS file"));
+        }
+
+        let size = UVec2::new(read_u32(bytes, 16)?, read_u32(bytes, 12)?);
+        let levels = read_u32(bytes, 28)?.max(1);
+        let pixel_flags = read_u32(bytes, 80)?;
+        let four_cc = read_u32(bytes, 84)?.to_le_bytes();
+        let caps2 = read_u32(bytes, 112)?;
+
+        if caps2 & (CUBEMAP | VOLUME) != 0 {
+            return Err(TextureError::Unsupported(
+                "DDS textures other than 2D".to_owned(),
+            ));
+        }
+
+        let mut data_offset = 128;
+        let format = if pixel_flags & FOURCC != 0 {
+            match &four_cc {
+                b"DX10" => {
+                    let dxgi = read_u32(bytes, 128)?;
+                    let array_size = read_u32(bytes, 140)?;
+                    if array_size > 1 {
+                        return Err(TextureError::Unsupported("DDS texture arrays".to_owned()));
+                    }
+                    data_offset += 20;
+
+                    dxgi_format(dxgi)
+                        .ok_or_else(|| TextureError::Unsupported(format!("DXGI format {dxgi}")))?
+                }
+                b"DXT1" => with_color_space(ImageFormat::Bc1RgbaUnorm, color_space),
+                b"DXT5" => with_color_space(ImageFormat::Bc3RgbaUnorm, color_space),
+                b"ATI1" | b"BC4U" => ImageFormat::Bc4RUnorm,
+                b"ATI2" | b"BC5U" => ImageFormat::Bc5RgUnorm,
+                _ => {
+                    return Err(TextureError::Unsupported(format!(
+                        "DDS FourCC {}",
+                        String::from_utf8_lossy(&four_cc)
+                    )))
+                }
+            }
+        } else if pixel_flags & RGB != 0
+            && read_u32(bytes, 88)? == 32
+            && read_u32(bytes, 92)? == 0xff
+            && read_u32(bytes, 96)? == 0xff00
+            && read_u32(bytes, 100)? == 0xff_0000
+            && read_u32(bytes, 104)? == 0xff00_0000
+        {
+            with_color_space(ImageFormat::Rgba8Unorm, color_space)
+        } else {
+            return Err(TextureError::Unsupported(
+                "DDS pixel layout other than RGBA8".to_owned(),
+            ));
+        };
+        check_size(size, levels)?;
+
+        let data = read_levels(bytes, data_offset, size, levels, format)?;
+        Ok(TextureData::with_mips(data, size, format))
+    }
+}
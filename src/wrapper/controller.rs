@@ -0,0 +1,258 @@
+use glam::Vec3;
+use glfw::{Action, Key, MouseButton, WindowEvent};
+
+use super::camera::Camera;
+
+/// Drives a camera's view from window input.
+pub trait CameraController {
+    fn handle_event(&mut self, event: &WindowEvent);
+
+    /// Advance by `delta` seconds and write the resulting view into `camera`.
+    fn update(&mut self, camera: &mut Camera, delta: f32);
+}
+
+/// Unit direction for a yaw around +Y (0 looking down -Z) and a pitch above the horizon.
+fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3::new(
+        -yaw.sin() * pitch.cos(),
+        pitch.sin(),
+        -yaw.cos() * pitch.cos(),
+    )
+}
+
+/// Inverse of `direction`, looking down -Z for a zero direction.
+fn yaw_pitch(direction: Vec3) -> (f32, f32) {
+    let Some(direction) = direction.try_normalize() else {
+        return (0., 0.);
+    };
+    (
+        (-direction.x).atan2(-direction.z),
+        direction.y.clamp(-1., 1.).asin(),
+    )
+}
+
+/// Keeps the camera from flipping over the poles.
+const MAX_PITCH: f32 = 89f32 * std::f32::consts::PI / 180.;
+
+/// Tracks cursor movement while a mouse button is held.
+#[derive(Default)]
+struct Drag {
+    active: bool,
+    last: Option<(f64, f64)>,
+    delta: (f64, f64),
+}
+
+impl Drag {
+    fn handle_event(&mut self, event: &WindowEvent, button: MouseButton) {
+        match *event {
+            WindowEvent::MouseButton(b, action, _) if b == button => {
+                self.active = action != Action::Release;
+                self.last = None;
+            }
+            WindowEvent::CursorPos(x, y) => {
+                if let (true, Some((last_x, last_y))) = (self.active, self.last) {
+                    self.delta.0 += x - last_x;
+                    self.delta.1 += y - last_y;
+                }
+                self.last = Some((x, y));
+            }
+            _ => {}
+        }
+    }
+
+    fn take(&mut self) -> (f32, f32) {
+        let delta = std::mem::take(&mut self.delta);
+        (delta.0 as f32, delta.1 as f32)
+    }
+}
+
+/// Free-flying camera: WASD to move, Space/Ctrl (or E/Q) to rise and sink, Shift to go faster
+/// and right mouse drag to look around.
+pub struct FlyController {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units per second.
+    pub speed: f32,
+    pub boost: f32,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: f32,
+    movement: [bool; 6],
+    boosting: bool,
+    drag: Drag,
+}
+
+impl FlyController {
+    pub fn new(position: Vec3, target: Vec3) -> Self {
+        let (yaw, pitch) = yaw_pitch(target - position);
+
+        Self {
+            position,
+            yaw,
+            pitch,
+            speed: 2.,
+            boost: 4.,
+            sensitivity: 0.003,
+            movement: [false; 6],
+            boosting: false,
+            drag: Drag::default(),
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn handle_event(&mut self, event: &WindowEvent) {
+        self.drag.handle_event(event, MouseButton::Button2);
+
+        if let WindowEvent::Key(key, _, action, _) = *event {
+            if action == Action::Repeat {
+                return;
+            }
+            let pressed = action == Action::Press;
+
+            let slot = match key {
+                Key::W => 0,
+                Key::S => 1,
+                Key::D => 2,
+                Key::A => 3,
+                Key::Space | Key::E => 4,
+                Key::LeftControl | Key::Q => 5,
+                Key::LeftShift => {
+                    self.boosting = pressed;
+                    return;
+                }
+                _ => return,
+            };
+            self.movement[slot] = pressed;
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, delta: f32) {
+        let (dx, dy) = self.drag.take();
+        self.yaw -= dx * self.sensitivity;
+        self.pitch = (self.pitch - dy * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let forward = direction(self.yaw, self.pitch);
+        let right = forward.cross(Vec3::Y).normalize();
+
+        let axis = |positive: usize, negative: usize| {
+            self.movement[positive] as i32 as f32 - self.movement[negative] as i32 as f32
+        };
+        let motion = forward * axis(0, 1) + right * axis(2, 3) + Vec3::Y * axis(4, 5);
+
+        let speed = if self.boosting {
+            self.speed * self.boost
+        } else {
+            self.speed
+        };
+        self.position += motion.normalize_or_zero() * speed * delta;
+
+        camera.set_view(&glam::Mat4::look_to_rh(self.position, forward, Vec3::Y));
+    }
+}
+
+/// Camera circling a target: left mouse drag to rotate, scroll to zoom.
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: f32,
+    /// Distance factor applied per scroll step.
+    pub zoom_step: f32,
+    drag: Drag,
+}
+
+impl OrbitController {
+    /// Orbit around `target`, starting from `eye`.
+    pub fn new(eye: Vec3, target: Vec3) -> Self {
+        // The orbit pitch is the angle above the target, opposite to the view's.
+        let (yaw, pitch) = yaw_pitch(target - eye);
+
+        Self {
+            target,
+            distance: eye.distance(target),
+            min_distance: 0.1,
+            max_distance: 100.,
+            yaw,
+            pitch: -pitch,
+            sensitivity: 0.005,
+            zoom_step: 0.9,
+            drag: Drag::default(),
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn handle_event(&mut self, event: &WindowEvent) {
+        self.drag.handle_event(event, MouseButton::Button1);
+
+        if let WindowEvent::Scroll(_, y) = *event {
+            self.distance = (self.distance * self.zoom_step.powf(y as f32))
+                .clamp(self.min_distance, self.max_distance);
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, _delta: f32) {
+        let (dx, dy) = self.drag.take();
+        self.yaw -= dx * self.sensitivity;
+        self.pitch = (self.pitch + dy * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+
+        // Looking at the target from the opposite side of the view direction.
+        let eye = self.target - direction(self.yaw, -self.pitch) * self.distance;
+        camera.set_view(&glam::Mat4::look_at_rh(eye, self.target, Vec3::Y));
+    }
+}
+
+/// Fixed viewpoint in front of the fireplace, gently swaying as if someone were sitting there.
+pub struct FireplaceViewer {
+    pub eye: Vec3,
+    pub target: Vec3,
+    /// Largest angular deviation of the gaze, in radians.
+    pub sway_amplitude: f32,
+    /// Largest displacement of the eye, in world units.
+    pub bob_amplitude: f32,
+    /// Sway cycles per second.
+    pub sway_frequency: f32,
+    time: f32,
+}
+
+impl FireplaceViewer {
+    pub fn new(eye: Vec3, target: Vec3) -> Self {
+        Self {
+            eye,
+            target,
+            sway_amplitude: 0.01,
+            bob_amplitude: 0.01,
+            sway_frequency: 0.1,
+            time: 0.,
+        }
+    }
+}
+
+impl CameraController for FireplaceViewer {
+    fn handle_event(&mut self, _event: &WindowEvent) {}
+
+    fn update(&mut self, camera: &mut Camera, delta: f32) {
+        use std::f32::consts::TAU;
+
+        self.time += delta;
+        let phase = self.time * self.sway_frequency * TAU;
+
+        // Incommensurate frequencies so the motion never visibly loops.
+        let sway_x = phase.sin() + 0.5 * (phase * 2.31).sin();
+        let sway_y = (phase * 0.77).sin() + 0.3 * (phase * 1.73).cos();
+
+        let (yaw, pitch) = yaw_pitch(self.target - self.eye);
+        let forward = direction(
+            yaw + sway_x / 1.5 * self.sway_amplitude,
+            pitch + sway_y / 1.3 * self.sway_amplitude,
+        );
+        let eye = self.eye + Vec3::Y * ((phase * 0.5).sin() * self.bob_amplitude);
+
+        camera.set_view(&glam::Mat4::look_to_rh(eye, forward, Vec3::Y));
+    }
+}
@@ -0,0 +1,235 @@
+//! Encoding of `TextureData` into PNG and Radiance HDR files.
+
+use std::{io::Write, path::Path};
+
+use super::{
+    hash::crc32,
+    image::{f16_to_f32, srgb_to_linear, TextureError},
+    texture::{ImageFormat, TextureData},
+};
+
+/// Largest payload of a stored deflate block.
+const STORED_BLOCK_SIZE: usize = 65535;
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+/// Wrap `bytes` in a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(bytes: &[u8]) -> Vec<u8> {
+    let block_count = bytes.len().div_ceil(STORED_BLOCK_SIZE).max(1);
+    let mut stream = Vec::with_capacity(bytes.len() + block_count * 5 + 6);
+
+    // Deflate with a 32K window, no preset dictionary, fastest compression.
+    stream.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = bytes.chunks(STORED_BLOCK_SIZE).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        stream.push(blocks.peek().is_none() as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(bytes).to_be_bytes());
+    stream
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    let mut chunk = Vec::with_capacity(data.len() + 4);
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(data);
+
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(&chunk)?;
+    writer.write_all(&crc32(&chunk).to_be_bytes())
+}
+
+/// Shared exponent encoding of a linear color.
+fn rgbe(color: [f32; 3]) -> [u8; 4] {
+    let [r, g, b] = color.map(|c| c.max(0.));
+    let max = r.max(g).max(b);
+    if max < 1e-32 {
+        return [0; 4];
+    }
+
+    // `max` is mantissa * 2^exponent with a mantissa in [0.5, 1).
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256. / 2f32.powi(exponent);
+    let [r, g, b] = [r, g, b].map(|c| (c * scale).min(255.) as u8);
+    [r, g, b, (exponent + 128) as u8]
+}
+
+impl TextureData {
+    /// Encode the full size image as PNG, rows going from top to bottom.
+    ///
+    /// 8-bit formats keep 8 bits and `Rgba16Unorm` keeps 16. Compressed formats are decoded
+    /// first, empty images are rejected as PNG cannot store them. The image data is stored
+    /// without compression, trading file size for a tiny encoder.
+    pub fn write_png(&self, writer: &mut impl Write) -> Result<(), TextureError> {
+        if self.format().is_compressed() {
+            return self.decompress().write_png(writer);
+        }
+
+        let size = self.size();
+        if size.x == 0 || size.y == 0 {
+            return Err(TextureError::Unsupported(format!(
+                "PNG export of empty {}x{} images",
+                size.x, size.y
+            )));
+        }
+
+        let (bit_depth, color_type) = match self.format() {
+            ImageFormat::Rgba8Unorm | ImageFormat::Rgba8SRgb => (8, 6),
+            ImageFormat::Rgb8Unorm | ImageFormat::Rgb8SRgb => (8, 2),
+            ImageFormat::Rgba16Unorm => (16, 6),
+            format => {
+                return Err(TextureError::Unsupported(format!(
+                    "PNG export of {format:?}"
+                )))
+            }
+        };
+
+        let row_len = size.x as usize * self.format().bytes_per_pixel();
+        let mut scanlines = Vec::with_capacity((row_len + 1) * size.y as usize);
+        for row in self.data().chunks_exact(row_len) {
+            // No filtering.
+            scanlines.push(0);
+            if bit_depth == 16 {
+                // PNG samples are big-endian.
+                scanlines.extend(
+                    row.chunks_exact(2)
+                        .flat_map(|s| u16::from_ne_bytes([s[0], s[1]]).to_be_bytes()),
+                );
+            } else {
+                scanlines.extend_from_slice(row);
+            }
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&size.x.to_be_bytes());
+        header.extend_from_slice(&size.y.to_be_bytes());
+        // Bit depth, color type, then default compression, filtering and no interlacing.
+        header.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+        write_chunk(writer, b"IHDR", &header)?;
+        write_chunk(writer, b"IDAT", &zlib_stored(&scanlines))?;
+        write_chunk(writer, b"IEND", &[])?;
+        Ok(())
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), TextureError> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_png(&mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Encode the full size image as a Radiance HDR file in linear color, dropping alpha.
+    ///
+    /// `Rgba16Float` keeps its range, normalized formats are linearized when sRGB. Empty images
+    /// are rejected like they are for PNG.
+    pub fn write_hdr(&self, writer: &mut impl Write) -> Result<(), TextureError> {
+        if self.format().is_compressed() {
+            return self.decompress().write_hdr(writer);
+        }
+
+        let size = self.size();
+        if size.x == 0 || size.y == 0 {
+            return Err(TextureError::Unsupported(format!(
+                "HDR export of empty {}x{} images",
+                size.x, size.y
+            )));
+        }
+
+        let data = self.data();
+        let pixels: Vec<[f32; 3]> = match self.format() {
+            ImageFormat::Rgba16Float => data
+                .chunks_exact(8)
+                .map(|p| {
+                    std::array::from_fn(|i| {
+                        f16_to_f32(u16::from_ne_bytes([p[2 * i], p[2 * i + 1]]))
+                    })
+                })
+                .collect(),
+            ImageFormat::Rgba16Unorm => data
+                .chunks_exact(8)
+                .map(|p| {
+                    std::array::from_fn(|i| {
+                        u16::from_ne_bytes([p[2 * i], p[2 * i + 1]]) as f32 / u16::MAX as f32
+                    })
+                })
+                .collect(),
+            format @ (ImageFormat::Rgba8Unorm
+            | ImageFormat::Rgba8SRgb
+            | ImageFormat::Rgb8Unorm
+            | ImageFormat::Rgb8SRgb) => {
+                let is_srgb = matches!(format, ImageFormat::Rgba8SRgb | ImageFormat::Rgb8SRgb);
+                data.chunks_exact(format.bytes_per_pixel())
+                    .map(|p| {
+                        std::array::from_fn(|i| {
+                            let value = p[i] as f32 / 255.;
+                            if is_srgb {
+                                srgb_to_linear(value)
+                            } else {
+                                value
+                            }
+                        })
+                    })
+                    .collect()
+            }
+            format => {
+                return Err(TextureError::Unsupported(format!(
+                    "HDR export of {format:?}"
+                )))
+            }
+        };
+
+        write!(
+            writer,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            size.y, size.x
+        )?;
+
+        for row in pixels.chunks_exact(size.x as usize) {
+            let row: Vec<[u8; 4]> = row.iter().map(|&c| rgbe(c)).collect();
+
+            if !(8..32768).contains(&size.x) {
+                // Widths the run-length scheme cannot encode are stored flat.
+                writer.write_all(row.as_flattened())?;
+                continue;
+            }
+
+            // Run-length scanlines, as flat ones could be mistaken for them. Each channel is
+            // written separately as literal runs of at most 128 bytes.
+            let width = size.x as u16;
+            writer.write_all(&[2, 2])?;
+            writer.write_all(&width.to_be_bytes())?;
+            for channel in 0..4 {
+                let values: Vec<u8> = row.iter().map(|c| c[channel]).collect();
+                for run in values.chunks(128) {
+                    writer.write_all(&[run.len() as u8])?;
+                    writer.write_all(run)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn save_hdr(&self, path: impl AsRef<Path>) -> Result<(), TextureError> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_hdr(&mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+}
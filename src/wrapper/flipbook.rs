@@ -0,0 +1,490 @@
+//! Animated sprites played from a sequence of frames, packed in an atlas or a texture array.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use glam::{UVec2, Vec2, Vec3};
+
+use super::{
+    hash::str_hash,
+    image::{ColorSpace, TextureError},
+    material::Material,
+    sampler::Sampler,
+    texture::{Texture, TextureData, TextureDimension},
+};
+
+/// Texture unit the frames are bound to by `Flipbook::apply`.
+pub const FLIPBOOK_UNIT: u32 = 13;
+
+/// Texture unit the motion vectors are bound to by `Flipbook::apply`.
+pub const FLIPBOOK_MOTION_UNIT: u32 = 14;
+
+/// Extensions of the files `FlipbookBuilder::frames_in` picks up.
+const FRAME_EXTENSIONS: [&str; 9] = [
+    "png", "jpg", "jpeg", "tga", "bmp", "psd", "hdr", "ktx2", "dds",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlipbookLayout {
+    /// Frames of a 2D texture in a grid, left to right from the first row of the image.
+    Atlas { columns: u32, rows: u32 },
+    /// One frame per layer of a `D2Array` texture, which avoids bleeding between frames.
+    Array,
+}
+
+/// How the frames are blended between, matching the `FLIPBOOK_*` constants of
+/// `flipbook.glsl`.
+#[derive(Clone)]
+pub enum FlipbookInterpolation {
+    /// Frames are held until the next one.
+    None,
+    /// Consecutive frames are cross-faded.
+    Blend,
+    /// Consecutive frames are warped toward each other before being cross-faded, which
+    /// hides the ghosting of plain blending at low frame rates.
+    ///
+    /// `texture` has the frames' layout, with how far each texel moves by the next frame in
+    /// its red and green channels, 0.5 meaning no motion. `strength` is the largest
+    /// distance, as a fraction of a frame.
+    MotionVectors { texture: Rc<Texture>, strength: f32 },
+}
+
+impl FlipbookInterpolation {
+    fn mode(&self) -> u32 {
+        match self {
+            FlipbookInterpolation::None => 0,
+            FlipbookInterpolation::Blend => 1,
+            FlipbookInterpolation::MotionVectors { .. } => 2,
+        }
+    }
+}
+
+/// Frames to show at some point of the animation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlipbookFrame {
+    pub current: u32,
+    pub next: u32,
+    /// Weight of `next`, 0 without interpolation.
+    pub blend: f32,
+}
+
+#[derive(Debug)]
+pub enum FlipbookError {
+    Texture(TextureError),
+    /// No frames were given, or the directory held no images.
+    NoFrames,
+    /// Frames disagree in size or format, or motion vectors in count.
+    Mismatch(String),
+}
+
+impl Display for FlipbookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlipbookError::Texture(err) => write!(f, "could not load frame: {err}"),
+            FlipbookError::NoFrames => write!(f, "flipbook has no frames"),
+            FlipbookError::Mismatch(reason) => write!(f, "invalid frames: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for FlipbookError {}
+
+impl From<TextureError> for FlipbookError {
+    fn from(value: TextureError) -> Self {
+        FlipbookError::Texture(value)
+    }
+}
+
+impl From<std::io::Error> for FlipbookError {
+    fn from(value: std::io::Error) -> Self {
+        FlipbookError::Texture(TextureError::Io(value))
+    }
+}
+
+/// A pre-rendered animation, such as a flame or smoke sequence.
+///
+/// Shaders including `flipbook.glsl` look frames up from a time, so particles can play the
+/// same flipbook at their own age.
+pub struct Flipbook {
+    texture: Rc<Texture>,
+    layout: FlipbookLayout,
+    frame_count: u32,
+    /// Frames played per second.
+    pub fps: f32,
+    /// Whether the animation starts over after the last frame, or holds it.
+    pub looping: bool,
+    pub interpolation: FlipbookInterpolation,
+}
+
+impl Flipbook {
+    /// Flipbook of the first `frame_count` frames of `texture`, without interpolation.
+    pub fn new(texture: Rc<Texture>, layout: FlipbookLayout, frame_count: u32, fps: f32) -> Self {
+        assert!(frame_count > 0, "Flipbook has no frames");
+        match layout {
+            FlipbookLayout::Atlas { columns, rows } => {
+                assert_eq!(
+                    texture.dimension(),
+                    TextureDimension::D2,
+                    "Flipbook atlases must be 2D textures"
+                );
+                assert!(
+                    frame_count <= columns * rows,
+                    "{frame_count} frames do not fit a {columns}x{rows} atlas"
+                );
+            }
+            FlipbookLayout::Array => {
+                assert!(
+                    matches!(texture.dimension(), TextureDimension::D2Array(layers) if frame_count <= layers),
+                    "Flipbook arrays need a 2D array texture with a layer per frame"
+                );
+            }
+        }
+
+        Self {
+            texture,
+            layout,
+            frame_count,
+            fps,
+            looping: true,
+            interpolation: FlipbookInterpolation::None,
+        }
+    }
+
+    pub fn builder() -> FlipbookBuilder {
+        FlipbookBuilder::default()
+    }
+
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    pub fn layout(&self) -> FlipbookLayout {
+        self.layout
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Size of a single frame in pixels.
+    pub fn frame_size(&self) -> UVec2 {
+        match self.layout {
+            FlipbookLayout::Atlas { columns, rows } => {
+                self.texture.size() / UVec2::new(columns, rows)
+            }
+            FlipbookLayout::Array => self.texture.size(),
+        }
+    }
+
+    /// Length of one playthrough in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frame_count as f32 / self.fps
+    }
+
+    /// Frames shown `time` seconds into the animation, as `flipbook_frame` finds them.
+    pub fn frame_at(&self, time: f32) -> FlipbookFrame {
+        let position = (time * self.fps).max(0.);
+        let mut current = position as u32;
+        let mut blend = position.fract();
+
+        let next = if self.looping {
+            current %= self.frame_count;
+            (current + 1) % self.frame_count
+        } else if current.saturating_add(1) >= self.frame_count {
+            current = self.frame_count - 1;
+            blend = 0.;
+            current
+        } else {
+            current + 1
+        };
+
+        if matches!(self.interpolation, FlipbookInterpolation::None) {
+            blend = 0.;
+        }
+        FlipbookFrame {
+            current,
+            next,
+            blend,
+        }
+    }
+
+    /// Texture coordinates of `uv` within `frame`, with the layer in `z` for arrays.
+    pub fn frame_uv(&self, frame: u32, uv: Vec2) -> Vec3 {
+        match self.layout {
+            FlipbookLayout::Atlas { columns, rows } => {
+                let cell = UVec2::new(frame % columns, frame / columns).as_vec2();
+                ((cell + uv.clamp(Vec2::ZERO, Vec2::ONE)) / UVec2::new(columns, rows).as_vec2())
+                    .extend(0.)
+            }
+            FlipbookLayout::Array => uv.extend(frame as f32),
+        }
+    }
+
+    /// Bind the frames and motion vectors to `FLIPBOOK_UNIT` and `FLIPBOOK_MOTION_UNIT` of
+    /// `material`, and set the uniforms of `flipbook.glsl` on its program.
+    ///
+    /// The uniforms belong to the program, so materials sharing it share the flipbook
+    /// settings.
+    pub fn apply(&self, material: &mut Material, sampler: &Rc<Sampler>) {
+        material.set_texture(FLIPBOOK_UNIT, &self.texture, sampler);
+
+        let strength = match &self.interpolation {
+            FlipbookInterpolation::MotionVectors { texture, strength } => {
+                material.set_texture(FLIPBOOK_MOTION_UNIT, texture, sampler);
+                *strength
+            }
+            _ => 0.,
+        };
+
+        let (columns, rows) = match self.layout {
+            FlipbookLayout::Atlas { columns, rows } => (columns, rows),
+            FlipbookLayout::Array => (1, 1),
+        };
+
+        let program = &material.program;
+        program.set_uniform_u32(str_hash("flipbook_columns"), columns);
+        program.set_uniform_u32(str_hash("flipbook_rows"), rows);
+        program.set_uniform_u32(str_hash("flipbook_frame_count"), self.frame_count);
+        program.set_uniform_f32(str_hash("flipbook_fps"), self.fps);
+        program.set_uniform_u32(str_hash("flipbook_looping"), self.looping as u32);
+        program.set_uniform_u32(
+            str_hash("flipbook_interpolation"),
+            self.interpolation.mode(),
+        );
+        program.set_uniform_f32(str_hash("flipbook_motion_strength"), strength);
+    }
+}
+
+/// Packs an image sequence from disk into a flipbook.
+pub struct FlipbookBuilder {
+    frames: Vec<PathBuf>,
+    motion_vectors: Option<(Vec<PathBuf>, f32)>,
+    color_space: ColorSpace,
+    fps: f32,
+    looping: bool,
+    blend: bool,
+    /// Atlas columns, or `None` for a square-ish grid.
+    columns: Option<u32>,
+    array: bool,
+}
+
+impl Default for FlipbookBuilder {
+    fn default() -> Self {
+        Self {
+            frames: Vec::new(),
+            motion_vectors: None,
+            color_space: ColorSpace::Srgb,
+            fps: 30.,
+            looping: true,
+            blend: false,
+            columns: None,
+            array: false,
+        }
+    }
+}
+
+/// Image files of `dir`, sorted by name so numbered sequences play in order.
+fn image_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_image = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| FRAME_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        if is_image && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Copy the base level of uncompressed `frames` into a grid.
+fn pack_atlas(frames: &[TextureData], columns: u32, rows: u32) -> TextureData {
+    let frame_size = frames[0].size();
+    let format = frames[0].format();
+    let size = frame_size * UVec2::new(columns, rows);
+
+    let frame_row = frame_size.x as usize * format.bytes_per_pixel();
+    let atlas_row = size.x as usize * format.bytes_per_pixel();
+    let mut pixels = vec![0u8; atlas_row * size.y as usize];
+
+    for (i, frame) in frames.iter().enumerate() {
+        let column = i % columns as usize;
+        let row = i / columns as usize;
+        for (y, line) in frame.data().chunks_exact(frame_row).enumerate() {
+            let start = (row * frame_size.y as usize + y) * atlas_row + column * frame_row;
+            pixels[start..start + frame_row].copy_from_slice(line);
+        }
+    }
+
+    TextureData::new(pixels, size, format)
+}
+
+impl FlipbookBuilder {
+    /// Append a frame.
+    pub fn frame(mut self, path: impl Into<PathBuf>) -> Self {
+        self.frames.push(path.into());
+        self
+    }
+
+    /// Append frames in order.
+    pub fn frames(mut self, paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        self.frames.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Append the images of `dir`, sorted by file name.
+    pub fn frames_in(self, dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(self.frames(image_files(dir.as_ref())?))
+    }
+
+    /// Warp between frames with motion vectors loaded from `paths`, one per frame, see
+    /// `FlipbookInterpolation::MotionVectors`.
+    pub fn motion_vectors(
+        mut self,
+        paths: impl IntoIterator<Item = impl Into<PathBuf>>,
+        strength: f32,
+    ) -> Self {
+        self.motion_vectors = Some((paths.into_iter().map(Into::into).collect(), strength));
+        self
+    }
+
+    /// Color space of the frames, sRGB by default.
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn fps(mut self, fps: f32) -> Self {
+        self.fps = fps;
+        self
+    }
+
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Cross-fade consecutive frames, unless motion vectors are given.
+    pub fn blend(mut self) -> Self {
+        self.blend = true;
+        self
+    }
+
+    /// Pack into an atlas `columns` frames wide, instead of a roughly square one.
+    pub fn columns(mut self, columns: u32) -> Self {
+        assert!(columns > 0, "Flipbook atlases need at least one column");
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Pack into a texture array instead of an atlas.
+    pub fn array(mut self) -> Self {
+        self.array = true;
+        self
+    }
+
+    pub fn build(self) -> Result<Flipbook, FlipbookError> {
+        if self.frames.is_empty() {
+            return Err(FlipbookError::NoFrames);
+        }
+
+        let frame_count = self.frames.len() as u32;
+        let layout = if self.array {
+            FlipbookLayout::Array
+        } else {
+            let columns = self
+                .columns
+                .unwrap_or_else(|| (frame_count as f32).sqrt().ceil() as u32)
+                .min(frame_count);
+            FlipbookLayout::Atlas {
+                columns,
+                rows: frame_count.div_ceil(columns),
+            }
+        };
+
+        let texture = Rc::new(load_frames(&self.frames, self.color_space, layout)?);
+        let mut flipbook = Flipbook::new(texture, layout, frame_count, self.fps);
+        flipbook.looping = self.looping;
+
+        if let Some((paths, strength)) = &self.motion_vectors {
+            if paths.len() != self.frames.len() {
+                return Err(FlipbookError::Mismatch(format!(
+                    "{} motion vector images for {} frames",
+                    paths.len(),
+                    self.frames.len()
+                )));
+            }
+            let texture = load_frames(paths, ColorSpace::Linear, layout)?;
+            if texture.size() != flipbook.texture.size() {
+                return Err(FlipbookError::Mismatch(
+                    "motion vectors and frames differ in size".to_owned(),
+                ));
+            }
+            flipbook.interpolation = FlipbookInterpolation::MotionVectors {
+                texture: Rc::new(texture),
+                strength: *strength,
+            };
+        } else if self.blend {
+            flipbook.interpolation = FlipbookInterpolation::Blend;
+        }
+
+        Ok(flipbook)
+    }
+}
+
+/// Load the images at `paths` into a texture of `layout`.
+fn load_frames(
+    paths: &[PathBuf],
+    color_space: ColorSpace,
+    layout: FlipbookLayout,
+) -> Result<Texture, FlipbookError> {
+    let frames = paths
+        .iter()
+        .map(|path| {
+            let frame = TextureData::from_file(path, color_space)?;
+            // Atlases are packed on the CPU, which takes whole pixels.
+            Ok(match layout {
+                FlipbookLayout::Atlas { .. } if frame.format().is_compressed() => {
+                    frame.decompress()
+                }
+                _ => frame,
+            })
+        })
+        .collect::<Result<Vec<_>, FlipbookError>>()?;
+
+    let first = &frames[0];
+    for (frame, path) in frames.iter().zip(paths) {
+        if frame.size() != first.size()
+            || frame.format() != first.format()
+            || (layout == FlipbookLayout::Array && frame.levels().len() != first.levels().len())
+        {
+            return Err(FlipbookError::Mismatch(format!(
+                "{path:?} is a {}x{} {:?} image with {} levels, unlike the first one, a {}x{} \
+                 {:?} image with {} levels",
+                frame.size().x,
+                frame.size().y,
+                frame.format(),
+                frame.levels().len(),
+                first.size().x,
+                first.size().y,
+                first.format(),
+                first.levels().len()
+            )));
+        }
+    }
+
+    Ok(match layout {
+        FlipbookLayout::Atlas { columns, rows } => {
+            Texture::new(&pack_atlas(&frames, columns, rows))
+        }
+        FlipbookLayout::Array => {
+            Texture::from_layers(TextureDimension::D2Array(frames.len() as u32), &frames)
+        }
+    })
+}
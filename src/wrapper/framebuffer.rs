@@ -0,0 +1,541 @@
+use std::{fmt::Display, rc::Rc};
+
+use gl::types::{GLenum, GLuint};
+use glam::{UVec2, Vec4};
+
+use crate::dogl;
+
+use super::{
+    handle::GLHandle,
+    texture::{mip_size, ImageFormat, SampleType, Texture},
+};
+
+/// Storage that can only be rendered to, possibly multisampled.
+pub struct Renderbuffer {
+    handle: GLHandle,
+    size: UVec2,
+    format: ImageFormat,
+    samples: u32,
+}
+
+impl Renderbuffer {
+    /// Renderbuffer with `samples` samples per pixel, 0 for a regular one.
+    pub fn new(size: UVec2, format: ImageFormat, samples: u32) -> Self {
+        assert!(
+            !format.is_compressed(),
+            "Cannot render to {format:?} renderbuffers"
+        );
+
+        let mut handle: GLuint = 0;
+        unsafe {
+            dogl!(gl::CreateRenderbuffers(1, &mut handle));
+            dogl!(gl::NamedRenderbufferStorageMultisample(
+                handle,
+                samples as i32,
+                format.to_gl().internal_format(),
+                size.x as i32,
+                size.y as i32
+            ));
+        }
+
+        Self {
+            handle: GLHandle::new(handle),
+            size,
+            format,
+            samples,
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &self.handle.get());
+        }
+    }
+}
+
+/// Image a framebuffer renders into.
+#[derive(Clone)]
+pub enum Attachment {
+    /// Mip `level` of a texture, either a single `layer` or all of them for layered rendering.
+    Texture {
+        texture: Rc<Texture>,
+        level: u32,
+        layer: Option<u32>,
+    },
+    Renderbuffer(Rc<Renderbuffer>),
+}
+
+impl From<Rc<Texture>> for Attachment {
+    fn from(texture: Rc<Texture>) -> Self {
+        Attachment::Texture {
+            texture,
+            level: 0,
+            layer: None,
+        }
+    }
+}
+
+impl From<Rc<Renderbuffer>> for Attachment {
+    fn from(renderbuffer: Rc<Renderbuffer>) -> Self {
+        Attachment::Renderbuffer(renderbuffer)
+    }
+}
+
+impl Attachment {
+    pub fn size(&self) -> UVec2 {
+        match self {
+            Attachment::Texture { texture, level, .. } => mip_size(texture.size(), *level),
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.size,
+        }
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        match self {
+            Attachment::Texture { texture, .. } => texture.format(),
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.format,
+        }
+    }
+
+    /// Samples per pixel, 0 when not multisampled.
+    pub fn samples(&self) -> u32 {
+        match self {
+            Attachment::Texture { .. } => 0,
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.samples,
+        }
+    }
+
+    fn attach(&self, framebuffer: GLuint, point: GLenum) {
+        unsafe {
+            match self {
+                Attachment::Texture {
+                    texture,
+                    level,
+                    layer: Some(layer),
+                } => {
+                    dogl!(gl::NamedFramebufferTextureLayer(
+                        framebuffer,
+                        point,
+                        texture.handle().get(),
+                        *level as i32,
+                        *layer as i32
+                    ));
+                }
+                Attachment::Texture { texture, level, .. } => {
+                    dogl!(gl::NamedFramebufferTexture(
+                        framebuffer,
+                        point,
+                        texture.handle().get(),
+                        *level as i32
+                    ));
+                }
+                Attachment::Renderbuffer(renderbuffer) => {
+                    dogl!(gl::NamedFramebufferRenderbuffer(
+                        framebuffer,
+                        point,
+                        gl::RENDERBUFFER,
+                        renderbuffer.handle.get()
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FramebufferError {
+    /// Attachments disagree in size or sample count, or are of the wrong kind.
+    Mismatch(String),
+    /// The driver rejected the combination of attachments, with the GL status.
+    Incomplete(GLenum),
+}
+
+impl Display for FramebufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FramebufferError::Mismatch(reason) => write!(f, "invalid attachments: {reason}"),
+            FramebufferError::Incomplete(status) => {
+                let reason = match *status {
+                    gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "incomplete attachment",
+                    gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "no attachments",
+                    gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "mixed sample counts",
+                    gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "mixed layered attachments",
+                    gl::FRAMEBUFFER_UNSUPPORTED => "unsupported format combination",
+                    _ => "unknown status",
+                };
+                write!(f, "incomplete framebuffer: {reason} ({status:#x})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FramebufferError {}
+
+/// Attachments of a framebuffer being built, with their clear values.
+#[derive(Default)]
+pub struct FramebufferBuilder {
+    colors: Vec<(Attachment, Option<Vec4>)>,
+    depth: Option<(Attachment, Option<f32>)>,
+    stencil_clear: Option<i32>,
+}
+
+impl FramebufferBuilder {
+    /// Add the next color attachment, written by fragment output `location = n` for the n-th.
+    pub fn color(mut self, attachment: impl Into<Attachment>, clear: Option<Vec4>) -> Self {
+        self.colors.push((attachment.into(), clear));
+        self
+    }
+
+    /// Set the depth attachment, also used for stencil when its format has one. Reverse-Z
+    /// depth clears to 0.
+    pub fn depth(mut self, attachment: impl Into<Attachment>, clear: Option<f32>) -> Self {
+        self.depth = Some((attachment.into(), clear));
+        self
+    }
+
+    pub fn stencil_clear(mut self, clear: Option<i32>) -> Self {
+        self.stencil_clear = clear;
+        self
+    }
+
+    pub fn build(self) -> Result<Framebuffer, FramebufferError> {
+        let first = self
+            .colors
+            .first()
+            .map(|(attachment, _)| attachment)
+            .or(self.depth.as_ref().map(|(attachment, _)| attachment))
+            .ok_or_else(|| FramebufferError::Mismatch("no attachments".to_owned()))?;
+        let (size, samples) = (first.size(), first.samples());
+
+        let depth_attachments = self.depth.iter().map(|(attachment, _)| attachment);
+        for attachment in self
+            .colors
+            .iter()
+            .map(|(attachment, _)| attachment)
+            .chain(depth_attachments)
+        {
+            if attachment.size() != size || attachment.samples() != samples {
+                return Err(FramebufferError::Mismatch(format!(
+                    "{}x{} with {} samples against {}x{} with {samples}",
+                    attachment.size().x,
+                    attachment.size().y,
+                    attachment.samples(),
+                    size.x,
+                    size.y
+                )));
+            }
+        }
+        if let Some((color, _)) = self.colors.iter().find(|(a, _)| a.format().is_depth()) {
+            return Err(FramebufferError::Mismatch(format!(
+                "color attachment has depth format {:?}",
+                color.format()
+            )));
+        }
+        if let Some((depth, _)) = &self.depth {
+            if !depth.format().is_depth() {
+                return Err(FramebufferError::Mismatch(format!(
+                    "depth attachment has color format {:?}",
+                    depth.format()
+                )));
+            }
+        }
+
+        let mut handle: GLuint = 0;
+        unsafe {
+            dogl!(gl::CreateFramebuffers(1, &mut handle));
+        }
+        // Owned right away, so that errors below release it.
+        let framebuffer = Framebuffer {
+            handle: GLHandle::new(handle),
+            size,
+            samples,
+            colors: self.colors,
+            depth: self.depth,
+            stencil_clear: self.stencil_clear,
+        };
+
+        let draw_buffers: Vec<GLenum> = (0..framebuffer.colors.len() as u32)
+            .map(|i| gl::COLOR_ATTACHMENT0 + i)
+            .collect();
+        for ((attachment, _), point) in framebuffer.colors.iter().zip(&draw_buffers) {
+            attachment.attach(handle, *point);
+        }
+        if let Some((attachment, _)) = &framebuffer.depth {
+            let point = if attachment.format().has_stencil() {
+                gl::DEPTH_STENCIL_ATTACHMENT
+            } else {
+                gl::DEPTH_ATTACHMENT
+            };
+            attachment.attach(handle, point);
+        }
+
+        unsafe {
+            if draw_buffers.is_empty() {
+                dogl!(gl::NamedFramebufferDrawBuffer(handle, gl::NONE));
+                dogl!(gl::NamedFramebufferReadBuffer(handle, gl::NONE));
+            } else {
+                dogl!(gl::NamedFramebufferDrawBuffers(
+                    handle,
+                    draw_buffers.len() as i32,
+                    draw_buffers.as_ptr()
+                ));
+            }
+
+            let status = gl::CheckNamedFramebufferStatus(handle, gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(FramebufferError::Incomplete(status));
+            }
+        }
+
+        Ok(framebuffer)
+    }
+}
+
+/// Render target made of color, depth and stencil attachments, each with its clear value.
+pub struct Framebuffer {
+    handle: GLHandle,
+    size: UVec2,
+    samples: u32,
+    colors: Vec<(Attachment, Option<Vec4>)>,
+    depth: Option<(Attachment, Option<f32>)>,
+    stencil_clear: Option<i32>,
+}
+
+impl Framebuffer {
+    pub fn builder() -> FramebufferBuilder {
+        FramebufferBuilder::default()
+    }
+
+    /// Multisampled color and depth renderbuffers, to be resolved into a regular framebuffer.
+    pub fn multisampled(
+        size: UVec2,
+        color: ImageFormat,
+        depth: ImageFormat,
+        samples: u32,
+        clear_color: Vec4,
+    ) -> Result<Self, FramebufferError> {
+        Self::builder()
+            .color(
+                Rc::new(Renderbuffer::new(size, color, samples)),
+                Some(clear_color),
+            )
+            .depth(Rc::new(Renderbuffer::new(size, depth, samples)), Some(0.))
+            .stencil_clear(depth.has_stencil().then_some(0))
+            .build()
+    }
+
+    pub fn handle(&self) -> &GLHandle {
+        &self.handle
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Samples per pixel, 0 when not multisampled.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn color(&self, index: usize) -> Option<&Attachment> {
+        self.colors.get(index).map(|(attachment, _)| attachment)
+    }
+
+    pub fn color_count(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn depth(&self) -> Option<&Attachment> {
+        self.depth.as_ref().map(|(attachment, _)| attachment)
+    }
+
+    /// Make this the target of the following draws, such as `Scene::render`, covering it
+    /// with the viewport.
+    pub fn bind(&self) {
+        unsafe {
+            dogl!(gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.handle.get()));
+            dogl!(gl::Viewport(0, 0, self.size.x as i32, self.size.y as i32));
+        }
+    }
+
+    /// Clear every attachment that has a clear value, within the scissor rectangle if enabled.
+    pub fn clear(&self) {
+        let handle = self.handle.get();
+
+        unsafe {
+            for (i, (attachment, clear)) in self.colors.iter().enumerate() {
+                let Some(color) = clear else {
+                    continue;
+                };
+                // Integer attachments are cleared to the truncated color.
+                match attachment.format().sample_type() {
+                    SampleType::Float => {
+                        dogl!(gl::ClearNamedFramebufferfv(
+                            handle,
+                            gl::COLOR,
+                            i as i32,
+                            color.as_ref().as_ptr()
+                        ));
+                    }
+                    SampleType::Uint => {
+                        dogl!(gl::ClearNamedFramebufferuiv(
+                            handle,
+                            gl::COLOR,
+                            i as i32,
+                            color.as_uvec4().as_ref().as_ptr()
+                        ));
+                    }
+                    SampleType::Sint => {
+                        dogl!(gl::ClearNamedFramebufferiv(
+                            handle,
+                            gl::COLOR,
+                            i as i32,
+                            color.as_ivec4().as_ref().as_ptr()
+                        ));
+                    }
+                }
+            }
+
+            let Some((depth, depth_clear)) = &self.depth else {
+                return;
+            };
+            let stencil_clear = self.stencil_clear.filter(|_| depth.format().has_stencil());
+
+            match (depth_clear, stencil_clear) {
+                (Some(depth), Some(stencil)) => {
+                    dogl!(gl::ClearNamedFramebufferfi(
+                        handle,
+                        gl::DEPTH_STENCIL,
+                        0,
+                        *depth,
+                        stencil
+                    ));
+                }
+                (Some(depth), None) => {
+                    dogl!(gl::ClearNamedFramebufferfv(handle, gl::DEPTH, 0, depth));
+                }
+                (None, Some(stencil)) => {
+                    dogl!(gl::ClearNamedFramebufferiv(
+                        handle,
+                        gl::STENCIL,
+                        0,
+                        &stencil
+                    ));
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// Copy the color attachments into those of `target` with the same index, and depth
+    /// along with them when both have it. Resolves multisampled attachments, which requires
+    /// both framebuffers to have the same size.
+    pub fn resolve(&self, target: &Framebuffer) {
+        assert!(
+            self.samples == 0 || self.size == target.size,
+            "Multisampled framebuffers resolve to framebuffers of the same size"
+        );
+
+        let count = self.colors.len().min(target.colors.len());
+        for i in 0..count {
+            let attachment = gl::COLOR_ATTACHMENT0 + i as u32;
+            unsafe {
+                dogl!(gl::NamedFramebufferReadBuffer(
+                    self.handle.get(),
+                    attachment
+                ));
+                dogl!(gl::NamedFramebufferDrawBuffer(
+                    target.handle.get(),
+                    attachment
+                ));
+            }
+            self.blit(target.handle.get(), target.size, gl::COLOR_BUFFER_BIT);
+        }
+
+        unsafe {
+            // Restore the attachments' default routing.
+            if !self.colors.is_empty() {
+                dogl!(gl::NamedFramebufferReadBuffer(
+                    self.handle.get(),
+                    gl::COLOR_ATTACHMENT0
+                ));
+            }
+            let draw_buffers: Vec<GLenum> = (0..target.colors.len() as u32)
+                .map(|i| gl::COLOR_ATTACHMENT0 + i)
+                .collect();
+            if !draw_buffers.is_empty() {
+                dogl!(gl::NamedFramebufferDrawBuffers(
+                    target.handle.get(),
+                    draw_buffers.len() as i32,
+                    draw_buffers.as_ptr()
+                ));
+            }
+        }
+
+        if let (Some((depth, _)), Some((target_depth, _))) = (&self.depth, &target.depth) {
+            let mut mask = gl::DEPTH_BUFFER_BIT;
+            if depth.format().has_stencil() && target_depth.format().has_stencil() {
+                mask |= gl::STENCIL_BUFFER_BIT;
+            }
+            self.blit(target.handle.get(), target.size, mask);
+        }
+    }
+
+    /// Copy the first color attachment to the window's back buffer of `window_size`,
+    /// stretching it to fit unless multisampled, as resolving requires the same size.
+    pub fn blit_to_window(&self, window_size: UVec2) {
+        assert!(
+            self.samples == 0 || self.size == window_size,
+            "Multisampled framebuffers resolve to windows of the same size"
+        );
+
+        self.blit(0, window_size, gl::COLOR_BUFFER_BIT);
+    }
+
+    fn blit(&self, target: GLuint, target_size: UVec2, mask: GLenum) {
+        // Only color can be filtered, and stretching needs filtering.
+        let filter = if mask == gl::COLOR_BUFFER_BIT && target_size != self.size {
+            gl::LINEAR
+        } else {
+            gl::NEAREST
+        };
+
+        unsafe {
+            dogl!(gl::BlitNamedFramebuffer(
+                self.handle.get(),
+                target,
+                0,
+                0,
+                self.size.x as i32,
+                self.size.y as i32,
+                0,
+                0,
+                target_size.x as i32,
+                target_size.y as i32,
+                mask,
+                filter
+            ));
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.handle.get());
+        }
+    }
+}
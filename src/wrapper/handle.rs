@@ -1,20 +1,30 @@
+use std::cell::Cell;
+
 use gl::types::GLuint;
 
 pub struct GLHandle {
-    handle: GLuint,
+    handle: Cell<GLuint>,
 }
 
 impl GLHandle {
     pub fn get(&self) -> GLuint {
-        self.handle
+        self.handle.get()
     }
 
     pub fn is_valid(&self) -> bool {
-        self.handle > 0
+        self.handle.get() > 0
     }
 
     pub fn new(handle: GLuint) -> Self {
-        Self { handle }
+        Self {
+            handle: Cell::new(handle),
+        }
+    }
+
+    /// Exchange the objects of two handles, so that an object can be recreated behind a
+    /// shared reference.
+    pub(crate) fn swap(&self, other: &GLHandle) {
+        self.handle.swap(&other.handle);
     }
 }
 
@@ -52,3 +62,30 @@ impl From<AccessType> for gl::types::GLenum {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrimitiveTopology {
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    Lines,
+    LineStrip,
+    Points,
+    /// Patches of the given vertex count, for tessellation shaders.
+    Patches(u32),
+}
+
+impl From<PrimitiveTopology> for gl::types::GLenum {
+    fn from(value: PrimitiveTopology) -> Self {
+        match value {
+            PrimitiveTopology::Triangles => gl::TRIANGLES,
+            PrimitiveTopology::TriangleStrip => gl::TRIANGLE_STRIP,
+            PrimitiveTopology::TriangleFan => gl::TRIANGLE_FAN,
+            PrimitiveTopology::Lines => gl::LINES,
+            PrimitiveTopology::LineStrip => gl::LINE_STRIP,
+            PrimitiveTopology::Points => gl::POINTS,
+            PrimitiveTopology::Patches(_) => gl::PATCHES,
+        }
+    }
+}
@@ -35,9 +35,18 @@ const LUT: [u32; 256] = [
 
 const SEED: u32 = 0xCAFECAFE;
 
-pub fn str_hash(str: &str) -> u32 {
-    let crc: u32 = !SEED;
-    !str.as_bytes().iter().fold(crc, |acc, e| {
+fn crc32_with_seed(seed: u32, bytes: &[u8]) -> u32 {
+    let crc: u32 = !seed;
+    !bytes.iter().fold(crc, |acc, e| {
         (acc >> 8) ^ LUT[((acc & 0xFF) ^ (*e as u32)) as usize]
     })
 }
+
+pub fn str_hash(str: &str) -> u32 {
+    crc32_with_seed(SEED, str.as_bytes())
+}
+
+/// Standard CRC-32 (ISO-HDLC) checksum, as used by zlib and PNG.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    crc32_with_seed(0, bytes)
+}
@@ -0,0 +1,265 @@
+//! Decoding of image files into `TextureData`.
+
+use std::{
+    ffi::CStr,
+    fmt::Display,
+    io::Read,
+    os::raw::c_int,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use stb_image::stb_image::{
+    stbi_failure_reason, stbi_image_free, stbi_is_16_bit_from_memory, stbi_is_hdr_from_memory,
+    stbi_load_16_from_memory, stbi_load_from_memory, stbi_loadf_from_memory,
+};
+
+use super::{
+    container,
+    texture::{ImageFormat, TextureData},
+};
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(std::io::Error),
+    /// The decoder rejected the image, with its reason.
+    Decode(String),
+    /// A valid file using a feature that is not handled.
+    Unsupported(String),
+}
+
+impl Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io(err) => write!(f, "I/O error: {err}"),
+            TextureError::Decode(reason) => write!(f, "could not decode image: {reason}"),
+            TextureError::Unsupported(feature) => write!(f, "unsupported {feature}"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<std::io::Error> for TextureError {
+    fn from(value: std::io::Error) -> Self {
+        TextureError::Io(value)
+    }
+}
+
+/// How the color channels of an 8 or 16-bit image are encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Colors meant to be displayed, such as albedo maps.
+    #[default]
+    Srgb,
+    /// Data that is not a color, such as normal or roughness maps.
+    Linear,
+}
+
+/// File an image was loaded from, and how, so that it can be loaded again.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureSource {
+    pub path: PathBuf,
+    pub color_space: ColorSpace,
+    /// Modification time of the file when it was read, if the platform reports one.
+    pub modified: Option<SystemTime>,
+}
+
+/// Convert to the nearest half-precision float, as bits.
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity, or a quiet NaN.
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Round to nearest, ties to even. A carry out of the mantissa correctly bumps the exponent.
+    let round = |value: u32, shift: u32| {
+        let remainder = value & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let truncated = value >> shift;
+        truncated + (remainder > halfway || (remainder == halfway && truncated & 1 == 1)) as u32
+    };
+
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // Subnormal, the implicit leading one becomes explicit.
+        return sign | round(mantissa | 0x80_0000, (14 - exponent) as u32) as u16;
+    }
+
+    sign | round(((exponent as u32) << 23) | mantissa, 13) as u16
+}
+
+/// Convert half-precision float bits to a float.
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1. } else { 1. };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0. => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1. + mantissa / 1024.) * 2f32.powi(exponent - 15),
+    }
+}
+
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn failure_reason() -> TextureError {
+    let reason = unsafe { stbi_failure_reason() };
+    TextureError::Decode(if reason.is_null() {
+        "unknown error".to_owned()
+    } else {
+        unsafe { CStr::from_ptr(reason) }
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+/// Decode with one of the stb loaders into RGBA pixels of `T`, copying them out of the stb
+/// allocation.
+unsafe fn decode<T: Copy>(
+    bytes: &[u8],
+    load: unsafe extern "C" fn(
+        *const u8,
+        c_int,
+        *mut c_int,
+        *mut c_int,
+        *mut c_int,
+        c_int,
+    ) -> *mut T,
+) -> Result<(Vec<T>, glam::UVec2), TextureError> {
+    let len = c_int::try_from(bytes.len())
+        .map_err(|_| TextureError::Decode("file too large".to_owned()))?;
+
+    let (mut width, mut height, mut channels) = (0, 0, 0);
+    let pixels = load(
+        bytes.as_ptr(),
+        len,
+        &mut width,
+        &mut height,
+        &mut channels,
+        4,
+    );
+    if pixels.is_null() {
+        return Err(failure_reason());
+    }
+
+    // Four components were requested, whatever the file's channel count.
+    let data = std::slice::from_raw_parts(pixels, width as usize * height as usize * 4).to_vec();
+    stbi_image_free(pixels.cast());
+
+    Ok((data, glam::uvec2(width as u32, height as u32)))
+}
+
+fn half_floats_to_bytes(values: impl Iterator<Item = f32>) -> Vec<u8> {
+    values.flat_map(|v| f32_to_f16(v).to_ne_bytes()).collect()
+}
+
+impl TextureData {
+    /// Load a PNG, JPEG, TGA, BMP, PSD, GIF, PIC, PNM or Radiance HDR image as RGBA, or a
+    /// KTX2 or DDS texture with its format and mip chain.
+    ///
+    /// 8-bit images keep their precision, tagged sRGB or linear according to `color_space`.
+    /// 16-bit images stay `Rgba16Unorm` when linear and are converted to linear `Rgba16Float`
+    /// otherwise, as there is no 16-bit sRGB format. HDR images always become `Rgba16Float`.
+    ///
+    /// The file is recorded as the source of the data and of textures created from it, for
+    /// `TextureWatcher` to reload.
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        color_space: ColorSpace,
+    ) -> Result<TextureData, TextureError> {
+        let path = path.as_ref();
+        let mut file = std::fs::File::open(path)?;
+        // Taken before reading, so that writes racing the read count as a later change.
+        let modified = file
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let mut data = Self::from_memory(&bytes, color_space)?;
+        data.set_source(TextureSource {
+            path: path.to_owned(),
+            color_space,
+            modified,
+        });
+        Ok(data)
+    }
+
+    /// Decode an image held in memory, see `from_file`.
+    pub fn from_memory(bytes: &[u8], color_space: ColorSpace) -> Result<TextureData, TextureError> {
+        if container::is_container(bytes) {
+            return match bytes[0] {
+                b'D' => Self::from_dds(bytes, color_space),
+                _ => Self::from_ktx2(bytes),
+            };
+        }
+
+        let len = c_int::try_from(bytes.len())
+            .map_err(|_| TextureError::Decode("file too large".to_owned()))?;
+
+        unsafe {
+            if stbi_is_hdr_from_memory(bytes.as_ptr(), len) != 0 {
+                let (pixels, size) = decode(bytes, stbi_loadf_from_memory)?;
+                let data = half_floats_to_bytes(pixels.into_iter());
+                return Ok(TextureData::new(data, size, ImageFormat::Rgba16Float));
+            }
+
+            if stbi_is_16_bit_from_memory(bytes.as_ptr(), len) != 0 {
+                let (pixels, size) = decode(bytes, stbi_load_16_from_memory)?;
+
+                return Ok(match color_space {
+                    ColorSpace::Linear => TextureData::new(
+                        pixels.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+                        size,
+                        ImageFormat::Rgba16Unorm,
+                    ),
+                    ColorSpace::Srgb => {
+                        let linear = pixels.iter().enumerate().map(|(i, &v)| {
+                            let v = v as f32 / u16::MAX as f32;
+                            // Alpha is always linear.
+                            if i % 4 == 3 {
+                                v
+                            } else {
+                                srgb_to_linear(v)
+                            }
+                        });
+                        TextureData::new(
+                            half_floats_to_bytes(linear),
+                            size,
+                            ImageFormat::Rgba16Float,
+                        )
+                    }
+                });
+            }
+
+            let (pixels, size) = decode(bytes, stbi_load_from_memory)?;
+            let format = match color_space {
+                ColorSpace::Srgb => ImageFormat::Rgba8SRgb,
+                ColorSpace::Linear => ImageFormat::Rgba8Unorm,
+            };
+            Ok(TextureData::new(pixels, size, format))
+        }
+    }
+}
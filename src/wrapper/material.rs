@@ -1,6 +1,6 @@
 use std::{collections::HashMap, rc::Rc};
 
-use super::{program::Program, texture::Texture};
+use super::{program::Program, sampler::Sampler, texture::Texture};
 
 pub enum BlendMode {
     None,
@@ -16,7 +16,8 @@ pub enum DepthTestMode {
 
 pub struct Material {
     pub program: Rc<Program>,
-    textures: HashMap<u32, Rc<Texture>>,
+    /// Texture and sampler bound to each texture unit.
+    textures: HashMap<u32, (Rc<Texture>, Rc<Sampler>)>,
 
     pub blend_mode: BlendMode,
     pub depth_test_mode: DepthTestMode,
@@ -31,11 +32,26 @@ impl Material {
             depth_test_mode: DepthTestMode::Standard,
         }
     }
-    pub fn set_texture(&mut self, slot: u32, texture: &Rc<Texture>) {
+    pub fn set_texture(&mut self, slot: u32, texture: &Rc<Texture>, sampler: &Rc<Sampler>) {
         self.textures
-            .entry(slot)
-            .and_modify(|tex| *tex = texture.clone())
-            .or_insert_with(|| texture.clone());
+            .insert(slot, (texture.clone(), sampler.clone()));
+    }
+
+    /// Change how the texture in `slot` is sampled.
+    pub fn set_sampler(&mut self, slot: u32, sampler: &Rc<Sampler>) {
+        let (_, current) = self
+            .textures
+            .get_mut(&slot)
+            .unwrap_or_else(|| panic!("No texture in slot {slot}"));
+        *current = sampler.clone();
+    }
+
+    pub fn texture(&self, slot: u32) -> Option<&Rc<Texture>> {
+        self.textures.get(&slot).map(|(texture, _)| texture)
+    }
+
+    pub fn sampler(&self, slot: u32) -> Option<&Rc<Sampler>> {
+        self.textures.get(&slot).map(|(_, sampler)| sampler)
     }
 
     pub fn bind(&self) {
@@ -64,8 +80,9 @@ impl Material {
                 }
             }
 
-            for (handle, texture) in self.textures.iter() {
-                texture.bind(*handle);
+            for (&slot, (texture, sampler)) in self.textures.iter() {
+                texture.bind(slot);
+                sampler.bind(slot);
             }
 
             self.program.bind();
@@ -1,159 +1,590 @@
-use crate::AsSlice;
+use std::{
+    cell::{Cell, Ref, RefCell},
+    path::Path,
+};
 
-use super::{buffer::GLBuffer, handle::BufferUsage};
+use crate::dogl;
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Vertex {
-    pub position: glam::Vec3,
-    pub normal: glam::Vec3,
-    pub color: glam::Vec3,
+use glam::Vec3;
+
+use super::{
+    bounds::{Aabb, BoundingSphere, Ray},
+    buffer::GLBuffer,
+    handle::{BufferUsage, PrimitiveTopology},
+    mesh_cache::{MeshCache, MeshCacheError},
+    mesh_data::{FromMeshData, LodChain, MeshData},
+    vertex::{deinterleave, InstanceData, Vertex, VertexFormat, MAX_VERTEX_ATTRIBUTES},
+};
+
+/// Anything a `SceneObject` can draw.
+pub trait Mesh {
+    fn draw(&self);
+
+    /// Object-space bounding sphere.
+    fn bounds(&self) -> BoundingSphere;
+
+    /// Object-space bounding box.
+    fn aabb(&self) -> Aabb;
+
+    fn lod_count(&self) -> usize {
+        1
+    }
+
+    /// Level of detail to use for a projected size given as a fraction of the viewport height.
+    fn select_lod(&self, _screen_size: f32) -> usize {
+        0
+    }
+
+    fn draw_lod(&self, _lod: usize) {
+        self.draw()
+    }
+
+    /// Draw `count` instances, reading per-instance attributes from `instances`.
+    fn draw_instanced(&self, instances: &GLBuffer<InstanceData>, count: usize) {
+        self.draw_lod_instanced(0, instances, count)
+    }
+
+    fn draw_lod_instanced(&self, lod: usize, instances: &GLBuffer<InstanceData>, count: usize);
+
+    /// Whether `raycast` can test the mesh's triangles.
+    fn has_pick_geometry(&self) -> bool {
+        false
+    }
+
+    /// Distance along an object-space ray to the nearest triangle, for meshes keeping their
+    /// geometry on the CPU.
+    fn raycast(&self, _ray: &Ray) -> Option<f32> {
+        None
+    }
 }
 
-impl Default for Vertex {
-    fn default() -> Self {
-        Self {
-            position: glam::vec3(0., 0., 0.),
-            normal: glam::vec3(0., 1., 0.),
-            color: glam::vec3(1., 1., 1.),
+/// Vertex indices of each triangle described by `indices`, none for non-triangle topologies.
+fn triangle_indices(
+    topology: PrimitiveTopology,
+    indices: &[u32],
+) -> Box<dyn Iterator<Item = [u32; 3]> + '_> {
+    match topology {
+        PrimitiveTopology::Triangles => {
+            Box::new(indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]))
+        }
+        PrimitiveTopology::TriangleStrip => {
+            Box::new(indices.windows(3).map(|t| [t[0], t[1], t[2]]))
+        }
+        PrimitiveTopology::TriangleFan => {
+            Box::new(indices.windows(2).skip(1).map(|t| [indices[0], t[0], t[1]]))
         }
+        _ => Box::new(std::iter::empty()),
     }
 }
 
-struct Range {
-    min: f32,
-    max: f32,
+/// Nearest hit of `ray` against the triangles of an indexed mesh. Triangles with a corner
+/// `position` does not find are skipped.
+fn raycast_indexed(
+    ray: &Ray,
+    topology: PrimitiveTopology,
+    indices: &[u32],
+    position: impl Fn(u32) -> Option<Vec3>,
+) -> Option<f32> {
+    triangle_indices(topology, indices)
+        .filter_map(|[a, b, c]| ray.intersect_triangle([position(a)?, position(b)?, position(c)?]))
+        .min_by(f32::total_cmp)
 }
 
-impl Range {
-    fn stretch_to(&mut self, v: f32) {
-        if v < self.min {
-            self.min = v;
-        } else if v > self.max {
-            self.max = v;
-        }
+/// CPU copy of a mesh's triangles, kept for precise picking.
+pub struct PickGeometry {
+    positions: Vec<Vec3>,
+    indices: Vec<u32>,
+}
+
+impl PickGeometry {
+    pub fn new(positions: Vec<Vec3>, indices: Vec<u32>) -> Self {
+        assert!(
+            indices.iter().all(|&i| (i as usize) < positions.len()),
+            "Pick geometry index out of bounds"
+        );
+        Self { positions, indices }
+    }
+
+    pub fn from_vertices<V: VertexFormat>(vertices: &[V], indices: &[u32]) -> Self {
+        Self::new(vertices.iter().map(V::position).collect(), indices.to_vec())
+    }
+
+    pub fn from_data(data: &MeshData) -> Self {
+        Self::new(data.positions.clone(), data.indices.clone())
+    }
+
+    fn raycast(&self, ray: &Ray, topology: PrimitiveTopology) -> Option<f32> {
+        raycast_indexed(ray, topology, &self.indices, |i| {
+            self.positions.get(i as usize).copied()
+        })
     }
 }
 
-impl From<f32> for Range {
-    fn from(value: f32) -> Self {
-        Self {
-            min: value,
-            max: value,
+/// Issue the draw call for the currently bound buffers, instanced when `instances` is given.
+fn draw_elements(
+    topology: PrimitiveTopology,
+    index_count: usize,
+    instances: Option<(&GLBuffer<InstanceData>, usize)>,
+) {
+    unsafe {
+        if let PrimitiveTopology::Patches(vertices) = topology {
+            dogl!(gl::PatchParameteri(gl::PATCH_VERTICES, vertices as i32));
+        }
+
+        match instances {
+            None => {
+                dogl!(gl::DrawElements(
+                    topology.into(),
+                    index_count as i32,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null(),
+                ));
+            }
+            Some((buffer, count)) => {
+                assert!(
+                    count <= buffer.size(),
+                    "Drawing {count} instances from a buffer of {}",
+                    buffer.size()
+                );
+
+                InstanceData::bind_buffer(buffer);
+
+                dogl!(gl::DrawElementsInstanced(
+                    topology.into(),
+                    index_count as i32,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null(),
+                    count as i32,
+                ));
+            }
         }
     }
 }
 
-struct Bounds {
-    x: Range,
-    y: Range,
-    z: Range,
+/// GPU storage for a mesh's vertices.
+enum VertexStreams<V> {
+    /// All attributes in a single buffer.
+    Interleaved(GLBuffer<V>),
+    /// One tightly packed buffer per attribute, in `V::ATTRIBUTES` order.
+    Separate(Vec<GLBuffer<u8>>),
 }
 
-impl Bounds {
-    pub fn new(mut vertices: impl Iterator<Item = glam::Vec3>) -> Self {
-        let first = vertices
-            .next()
-            .expect("Cannot create bounds from empty vertices vector");
-
-        let mut res = Self::from(first);
+impl<V: VertexFormat> VertexStreams<V> {
+    fn new(vertices: &[V], interleaved: bool, dynamic: bool) -> Self {
+        fn buffer<T>(data: &[T], dynamic: bool) -> GLBuffer<T> {
+            if dynamic {
+                GLBuffer::new_dynamic(data)
+            } else {
+                GLBuffer::new(data)
+            }
+        }
 
-        for v in vertices {
-            res.stretch_to(v);
+        if interleaved {
+            VertexStreams::Interleaved(buffer(vertices, dynamic))
+        } else {
+            VertexStreams::Separate(
+                deinterleave(vertices)
+                    .iter()
+                    .map(|stream| buffer(stream, dynamic))
+                    .collect(),
+            )
         }
+    }
 
-        res
+    fn len(&self) -> usize {
+        match self {
+            VertexStreams::Interleaved(buffer) => buffer.size(),
+            VertexStreams::Separate(buffers) => buffers
+                .first()
+                .map_or(0, |buffer| buffer.size() / V::ATTRIBUTES[0].byte_size()),
+        }
     }
 
-    pub fn stretch_to(&mut self, pos: glam::Vec3) {
-        self.x.stretch_to(pos.x);
-        self.y.stretch_to(pos.y);
-        self.z.stretch_to(pos.z);
+    fn is_interleaved(&self) -> bool {
+        matches!(self, VertexStreams::Interleaved(_))
     }
 
-    pub fn get_center(&self) -> glam::Vec3 {
-        glam::Vec3 {
-            x: (self.x.min + self.x.max) / 2.,
-            y: (self.y.min + self.y.max) / 2.,
-            z: (self.z.min + self.z.max) / 2.,
+    fn update(&self, start: usize, vertices: &[V]) {
+        match self {
+            VertexStreams::Interleaved(buffer) => buffer.update(start, vertices),
+            VertexStreams::Separate(buffers) => {
+                let streams = deinterleave(vertices);
+                for ((attribute, buffer), stream) in V::ATTRIBUTES.iter().zip(buffers).zip(streams)
+                {
+                    buffer.update(start * attribute.byte_size(), &stream);
+                }
+            }
         }
     }
 
-    pub fn get_min(&self) -> glam::Vec3 {
-        glam::vec3(self.x.min, self.y.min, self.z.min)
+    fn bind(&self) {
+        match self {
+            VertexStreams::Interleaved(buffer) => {
+                buffer.bind(BufferUsage::Attribute);
+                for attribute in V::ATTRIBUTES {
+                    attribute.bind_pointer(std::mem::size_of::<V>(), attribute.offset, 0);
+                }
+            }
+            VertexStreams::Separate(buffers) => {
+                for (attribute, buffer) in V::ATTRIBUTES.iter().zip(buffers) {
+                    buffer.bind(BufferUsage::Attribute);
+                    attribute.bind_pointer(attribute.byte_size(), 0, 0);
+                }
+            }
+        }
+
+        // Locations left enabled by a previously drawn format would read stale buffers.
+        for location in 0..MAX_VERTEX_ATTRIBUTES {
+            if !V::ATTRIBUTES.iter().any(|a| a.location == location) {
+                unsafe { gl::DisableVertexAttribArray(location) };
+            }
+        }
     }
+}
 
-    pub fn get_max(&self) -> glam::Vec3 {
-        glam::vec3(self.x.max, self.y.max, self.z.max)
+struct Lod {
+    index_buffer: GLBuffer<u32>,
+    min_screen_size: f32,
+}
+
+impl Lod {
+    fn new(indices: &[u32], min_screen_size: f32) -> Self {
+        Self {
+            index_buffer: GLBuffer::new(indices),
+            min_screen_size,
+        }
     }
 }
 
-impl From<glam::Vec3> for Bounds {
-    fn from(value: glam::Vec3) -> Self {
+pub struct StaticMesh<V: VertexFormat = Vertex> {
+    vertex_streams: VertexStreams<V>,
+    /// Levels of detail from most to least detailed, all sharing `vertex_streams`.
+    lods: Vec<Lod>,
+    aabb: Aabb,
+    bounding_sphere: BoundingSphere,
+    topology: PrimitiveTopology,
+    pick_geometry: Option<PickGeometry>,
+}
+
+impl<V: VertexFormat> StaticMesh<V> {
+    /// Create a mesh storing its vertices in a single interleaved buffer.
+    pub fn new(vertices: &[V], indices: &[u32]) -> Self {
+        Self::with_streams(vertices, vec![Lod::new(indices, 0.)], true)
+    }
+
+    /// Create a mesh storing each vertex attribute in its own buffer.
+    pub fn new_separate(vertices: &[V], indices: &[u32]) -> Self {
+        Self::with_streams(vertices, vec![Lod::new(indices, 0.)], false)
+    }
+
+    pub fn from_data(data: &MeshData) -> Self
+    where
+        V: FromMeshData,
+    {
+        Self::new(&data.to_vertices(), &data.indices)
+    }
+
+    /// Create a mesh drawn with one of the chain's index buffers depending on its screen size.
+    pub fn with_lods(vertices: &[V], chain: &LodChain) -> Self {
+        assert!(!chain.levels.is_empty(), "LOD chain must not be empty");
+
+        let lods = chain
+            .levels
+            .iter()
+            .map(|level| Lod::new(&level.indices, level.min_screen_size))
+            .collect();
+
+        Self::with_streams(vertices, lods, true)
+    }
+
+    pub fn from_lod_chain(data: &MeshData, chain: &LodChain) -> Self
+    where
+        V: FromMeshData,
+    {
+        Self::with_lods(&data.to_vertices(), chain)
+    }
+
+    /// Upload a parsed mesh cache, whose layout must match `V`.
+    pub fn from_cache(cache: &MeshCache) -> Result<Self, MeshCacheError> {
+        if !cache.matches_format::<V>() {
+            return Err(MeshCacheError::LayoutMismatch);
+        }
+
+        Ok(Self {
+            vertex_streams: VertexStreams::Interleaved(GLBuffer::from_bytes(cache.vertex_bytes)),
+            lods: vec![Lod {
+                index_buffer: GLBuffer::from_bytes(cache.index_bytes),
+                min_screen_size: 0.,
+            }],
+            aabb: cache.aabb,
+            bounding_sphere: cache.bounding_sphere,
+            topology: cache.topology,
+            pick_geometry: None,
+        })
+    }
+
+    /// Read, validate and upload a mesh cache file.
+    pub fn load_cache(path: impl AsRef<Path>) -> Result<Self, MeshCacheError> {
+        let bytes = std::fs::read(path)?;
+        Self::from_cache(&MeshCache::parse(&bytes)?)
+    }
+
+    fn with_streams(vertices: &[V], lods: Vec<Lod>, interleaved: bool) -> Self {
         Self {
-            x: Range::from(value.x),
-            y: Range::from(value.y),
-            z: Range::from(value.z),
+            vertex_streams: VertexStreams::new(vertices, interleaved, false),
+            lods,
+            aabb: Aabb::from_points(vertices.iter().map(V::position)),
+            bounding_sphere: BoundingSphere::from_points(vertices.iter().map(V::position)),
+            topology: PrimitiveTopology::Triangles,
+            pick_geometry: None,
         }
     }
+
+    /// Interpret the indices with the given topology instead of as a triangle list.
+    pub fn with_topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    /// Keep a CPU copy of the triangles so that picking can test them instead of the bounds.
+    pub fn with_pick_geometry(mut self, geometry: PickGeometry) -> Self {
+        self.pick_geometry = Some(geometry);
+        self
+    }
+
+    pub fn is_interleaved(&self) -> bool {
+        self.vertex_streams.is_interleaved()
+    }
+
+    fn lod(&self, lod: usize) -> &Lod {
+        &self.lods[lod.min(self.lods.len() - 1)]
+    }
 }
 
-pub struct BoundingSphere {
-    pub center: glam::Vec3,
-    pub radius: f32,
+impl<V: VertexFormat> Mesh for StaticMesh<V> {
+    fn draw(&self) {
+        self.draw_lod(0);
+    }
+
+    fn bounds(&self) -> BoundingSphere {
+        self.bounding_sphere
+    }
+
+    fn aabb(&self) -> Aabb {
+        self.aabb
+    }
+
+    fn lod_count(&self) -> usize {
+        self.lods.len()
+    }
+
+    fn select_lod(&self, screen_size: f32) -> usize {
+        self.lods
+            .iter()
+            .position(|lod| screen_size >= lod.min_screen_size)
+            .unwrap_or(self.lods.len() - 1)
+    }
+
+    fn draw_lod(&self, lod: usize) {
+        let lod = self.lod(lod);
+
+        self.vertex_streams.bind();
+        lod.index_buffer.bind(BufferUsage::Index);
+        draw_elements(self.topology, lod.index_buffer.size(), None);
+    }
+
+    fn draw_lod_instanced(&self, lod: usize, instances: &GLBuffer<InstanceData>, count: usize) {
+        let lod = self.lod(lod);
+
+        self.vertex_streams.bind();
+        lod.index_buffer.bind(BufferUsage::Index);
+        draw_elements(
+            self.topology,
+            lod.index_buffer.size(),
+            Some((instances, count)),
+        );
+    }
+
+    fn has_pick_geometry(&self) -> bool {
+        self.pick_geometry.is_some()
+    }
+
+    fn raycast(&self, ray: &Ray) -> Option<f32> {
+        self.pick_geometry
+            .as_ref()
+            .and_then(|geometry| geometry.raycast(ray, self.topology))
+    }
 }
 
-pub struct StaticMesh {
-    vertex_buffer: GLBuffer<Vertex>,
-    index_buffer: GLBuffer<u32>,
-    bounding_sphere: BoundingSphere,
+/// Mesh whose vertices and indices can be rewritten after creation.
+///
+/// A CPU copy of the vertices and indices is kept so that partial updates can recompute the
+/// bounds and picking can test triangles. All updates go through `&self` so that the mesh can
+/// stay shared with the `SceneObject`s using it.
+pub struct DynamicMesh<V: VertexFormat = Vertex> {
+    vertices: RefCell<Vec<V>>,
+    vertex_streams: RefCell<VertexStreams<V>>,
+    index_buffer: RefCell<GLBuffer<u32>>,
+    indices: RefCell<Vec<u32>>,
+    aabb: Cell<Aabb>,
+    bounding_sphere: Cell<BoundingSphere>,
+    topology: Cell<PrimitiveTopology>,
 }
 
-impl StaticMesh {
-    pub fn new(vertices: &[Vertex], indices: &[u32]) -> Self {
-        let bounds = Bounds::new(vertices.iter().map(|v| v.position));
-        let center = bounds.get_center();
-
-        unsafe {
-            Self {
-                vertex_buffer: GLBuffer::new(vertices),
-                index_buffer: GLBuffer::new(indices),
-                bounding_sphere: BoundingSphere {
-                    center,
-                    radius: bounds
-                        .get_min()
-                        .distance(center)
-                        .max(bounds.get_max().distance(center)),
-                },
-            }
+impl<V: VertexFormat> DynamicMesh<V> {
+    pub fn new(vertices: &[V], indices: &[u32]) -> Self {
+        Self::with_streams(vertices, indices, true)
+    }
+
+    pub fn new_separate(vertices: &[V], indices: &[u32]) -> Self {
+        Self::with_streams(vertices, indices, false)
+    }
+
+    fn with_streams(vertices: &[V], indices: &[u32], interleaved: bool) -> Self {
+        Self {
+            vertices: RefCell::new(vertices.to_vec()),
+            vertex_streams: RefCell::new(VertexStreams::new(vertices, interleaved, true)),
+            index_buffer: RefCell::new(GLBuffer::new_dynamic(indices)),
+            indices: RefCell::new(indices.to_vec()),
+            aabb: Cell::new(Aabb::from_points(vertices.iter().map(V::position))),
+            bounding_sphere: Cell::new(BoundingSphere::from_points(
+                vertices.iter().map(V::position),
+            )),
+            topology: Cell::new(PrimitiveTopology::Triangles),
         }
     }
 
-    pub fn draw(&self) {
-        self.vertex_buffer.bind(BufferUsage::Attribute);
-        self.index_buffer.bind(BufferUsage::Index);
-
-        unsafe {
-            gl::VertexAttribPointer(
-                0,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                std::mem::size_of::<Vertex>() as i32,
-                std::ptr::null(),
-            );
-
-            gl::VertexAttribPointer(
-                1,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                std::mem::size_of::<Vertex>() as i32,
-                (3 * std::mem::size_of::<f32>()) as *mut std::ffi::c_void,
-            )
-        };
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology.get()
+    }
+
+    pub fn set_topology(&self, topology: PrimitiveTopology) {
+        self.topology.set(topology);
+    }
+
+    pub fn vertices(&self) -> Ref<'_, [V]> {
+        Ref::map(self.vertices.borrow(), Vec::as_slice)
+    }
+
+    pub fn indices(&self) -> Ref<'_, [u32]> {
+        Ref::map(self.indices.borrow(), Vec::as_slice)
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.indices.borrow().len()
+    }
+
+    /// Replace every vertex, growing the GPU storage if needed.
+    pub fn set_vertices(&self, vertices: &[V]) {
+        let mut streams = self.vertex_streams.borrow_mut();
+        if vertices.len() > streams.len() {
+            *streams = VertexStreams::new(vertices, streams.is_interleaved(), true);
+        } else {
+            streams.update(0, vertices);
+        }
+
+        *self.vertices.borrow_mut() = vertices.to_vec();
+        self.recompute_bounds();
+    }
+
+    /// Overwrite the vertices starting at `start`.
+    pub fn update_vertices(&self, start: usize, vertices: &[V]) {
+        self.modify_vertices(start..start + vertices.len(), |range| {
+            range.clone_from_slice(vertices)
+        });
+    }
+
+    /// Edit a range of the CPU copy in place and upload only that range.
+    pub fn modify_vertices(&self, range: std::ops::Range<usize>, f: impl FnOnce(&mut [V])) {
+        let mut vertices = self.vertices.borrow_mut();
+        assert!(
+            range.end <= vertices.len(),
+            "Vertex range {range:?} out of bounds ({})",
+            vertices.len()
+        );
+
+        f(&mut vertices[range.clone()]);
+        self.vertex_streams
+            .borrow()
+            .update(range.start, &vertices[range]);
+
+        drop(vertices);
+        self.recompute_bounds();
+    }
+
+    /// Replace every index, growing the GPU storage if needed.
+    pub fn set_indices(&self, indices: &[u32]) {
+        let mut buffer = self.index_buffer.borrow_mut();
+        if indices.len() > buffer.size() {
+            *buffer = GLBuffer::new_dynamic(indices);
+        } else {
+            buffer.update(0, indices);
+        }
+
+        *self.indices.borrow_mut() = indices.to_vec();
+    }
+
+    /// Overwrite the indices starting at `start`, which must be within the current index count.
+    pub fn update_indices(&self, start: usize, indices: &[u32]) {
+        assert!(
+            start + indices.len() <= self.index_count(),
+            "Index range out of bounds"
+        );
+        self.index_buffer.borrow().update(start, indices);
+        self.indices.borrow_mut()[start..start + indices.len()].copy_from_slice(indices);
+    }
+
+    fn bind(&self) {
+        self.vertex_streams.borrow().bind();
+        self.index_buffer.borrow().bind(BufferUsage::Index);
+    }
+
+    fn recompute_bounds(&self) {
+        let vertices = self.vertices.borrow();
+        self.aabb
+            .set(Aabb::from_points(vertices.iter().map(V::position)));
+        self.bounding_sphere.set(BoundingSphere::from_points(
+            vertices.iter().map(V::position),
+        ));
+    }
+}
+
+impl<V: VertexFormat> Mesh for DynamicMesh<V> {
+    fn draw(&self) {
+        self.bind();
+        draw_elements(self.topology(), self.index_count(), None);
+    }
+
+    fn bounds(&self) -> BoundingSphere {
+        self.bounding_sphere.get()
+    }
+
+    fn aabb(&self) -> Aabb {
+        self.aabb.get()
+    }
+
+    fn draw_lod_instanced(&self, _lod: usize, instances: &GLBuffer<InstanceData>, count: usize) {
+        self.bind();
+        draw_elements(
+            self.topology(),
+            self.index_count(),
+            Some((instances, count)),
+        );
+    }
+
+    fn has_pick_geometry(&self) -> bool {
+        true
     }
 
-    pub fn bounds(&self) -> &BoundingSphere {
-        &self.bounding_sphere
+    fn raycast(&self, ray: &Ray) -> Option<f32> {
+        let vertices = self.vertices.borrow();
+        // Vertices and indices are replaced separately, so they may not match in between.
+        raycast_indexed(ray, self.topology(), &self.indices.borrow(), |i| {
+            vertices.get(i as usize).map(V::position)
+        })
     }
 }
@@ -0,0 +1,533 @@
+//! Compact binary mesh format.
+//!
+//! All values, including every vertex attribute component, are little-endian and every
+//! section starts on a 16-byte boundary, so on little-endian hosts a memory-mapped file can be
+//! handed to [`MeshCache::parse`] and its blobs uploaded as-is.
+//!
+//! ```text
+//! header      80 bytes, see `to_bytes`
+//! attributes  20 bytes each: location, components, component type, kind, offset
+//! vertices    vertex_stride * vertex_count bytes
+//! indices     4 * index_count bytes
+//! checksum    CRC-32 of every preceding byte
+//! ```
+
+use std::{fmt::Display, io::Write, path::Path};
+
+use super::{
+    bounds::{Aabb, BoundingSphere},
+    handle::PrimitiveTopology,
+    hash::crc32,
+    vertex::{copy_attribute, AttributeKind, ComponentType, VertexAttribute, VertexFormat},
+};
+
+pub const MAGIC: [u8; 8] = *b"SFMESH\0\0";
+pub const VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 80;
+const ATTRIBUTE_SIZE: usize = 20;
+const ALIGNMENT: usize = 16;
+
+#[derive(Debug)]
+pub enum MeshCacheError {
+    Io(std::io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    ChecksumMismatch {
+        expected: u32,
+        found: u32,
+    },
+    InvalidData(&'static str),
+    /// An index refers past the stored vertices.
+    IndexOutOfRange {
+        index: u32,
+        vertex_count: usize,
+    },
+    /// The stored vertex layout differs from the requested vertex format.
+    LayoutMismatch,
+}
+
+impl Display for MeshCacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshCacheError::Io(err) => write!(f, "I/O error: {err}"),
+            MeshCacheError::InvalidMagic => write!(f, "not a mesh cache file"),
+            MeshCacheError::UnsupportedVersion(version) => {
+                write!(f, "unsupported mesh cache version {version}")
+            }
+            MeshCacheError::Truncated => write!(f, "mesh cache is truncated"),
+            MeshCacheError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "checksum mismatch (expected {expected:#010x}, found {found:#010x})"
+            ),
+            MeshCacheError::InvalidData(what) => write!(f, "invalid mesh cache: {what}"),
+            MeshCacheError::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => write!(f, "index {index} out of range for {vertex_count} vertices"),
+            MeshCacheError::LayoutMismatch => {
+                write!(f, "stored vertex layout does not match the vertex format")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MeshCacheError {}
+
+impl From<std::io::Error> for MeshCacheError {
+    fn from(value: std::io::Error) -> Self {
+        MeshCacheError::Io(value)
+    }
+}
+
+/// Parsed view over the bytes of a mesh cache file.
+pub struct MeshCache<'a> {
+    pub attributes: Vec<VertexAttribute>,
+    pub vertex_stride: usize,
+    pub vertex_count: usize,
+    pub topology: PrimitiveTopology,
+    pub aabb: Aabb,
+    pub bounding_sphere: BoundingSphere,
+    /// Raw interleaved vertices, with little-endian components.
+    pub vertex_bytes: &'a [u8],
+    /// Raw little-endian `u32` indices.
+    pub index_bytes: &'a [u8],
+}
+
+fn align(offset: usize) -> usize {
+    offset.next_multiple_of(ALIGNMENT)
+}
+
+fn topology_to_code(topology: PrimitiveTopology) -> (u32, u32) {
+    match topology {
+        PrimitiveTopology::Triangles => (0, 0),
+        PrimitiveTopology::TriangleStrip => (1, 0),
+        PrimitiveTopology::TriangleFan => (2, 0),
+        PrimitiveTopology::Lines => (3, 0),
+        PrimitiveTopology::LineStrip => (4, 0),
+        PrimitiveTopology::Points => (5, 0),
+        PrimitiveTopology::Patches(vertices) => (6, vertices),
+    }
+}
+
+fn topology_from_code(code: u32, patch_vertices: u32) -> Option<PrimitiveTopology> {
+    Some(match code {
+        0 => PrimitiveTopology::Triangles,
+        1 => PrimitiveTopology::TriangleStrip,
+        2 => PrimitiveTopology::TriangleFan,
+        3 => PrimitiveTopology::Lines,
+        4 => PrimitiveTopology::LineStrip,
+        5 => PrimitiveTopology::Points,
+        6 => PrimitiveTopology::Patches(patch_vertices),
+        _ => return None,
+    })
+}
+
+fn component_type_to_code(ty: ComponentType) -> u32 {
+    match ty {
+        ComponentType::F32 => 0,
+        ComponentType::U8 => 1,
+        ComponentType::U16 => 2,
+        ComponentType::U32 => 3,
+        ComponentType::I8 => 4,
+        ComponentType::I16 => 5,
+        ComponentType::I32 => 6,
+    }
+}
+
+fn component_type_from_code(code: u32) -> Option<ComponentType> {
+    Some(match code {
+        0 => ComponentType::F32,
+        1 => ComponentType::U8,
+        2 => ComponentType::U16,
+        3 => ComponentType::U32,
+        4 => ComponentType::I8,
+        5 => ComponentType::I16,
+        6 => ComponentType::I32,
+        _ => return None,
+    })
+}
+
+fn kind_to_code(kind: AttributeKind) -> u32 {
+    match kind {
+        AttributeKind::Float => 0,
+        AttributeKind::Normalized => 1,
+        AttributeKind::Integer => 2,
+    }
+}
+
+fn kind_from_code(code: u32) -> Option<AttributeKind> {
+    Some(match code {
+        0 => AttributeKind::Float,
+        1 => AttributeKind::Normalized,
+        2 => AttributeKind::Integer,
+        _ => return None,
+    })
+}
+
+/// Little-endian cursor over a byte slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MeshCacheError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .ok_or(MeshCacheError::Truncated)?;
+        let slice = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or(MeshCacheError::Truncated)?;
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, MeshCacheError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, MeshCacheError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn vec3(&mut self) -> Result<glam::Vec3, MeshCacheError> {
+        Ok(glam::vec3(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    fn align(&mut self) {
+        self.offset = align(self.offset);
+    }
+}
+
+impl<'a> MeshCache<'a> {
+    /// Validate and parse a mesh cache, borrowing its vertex and index blobs.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, MeshCacheError> {
+        if bytes.len() < HEADER_SIZE + 4 {
+            return Err(MeshCacheError::Truncated);
+        }
+        if bytes[..8] != MAGIC {
+            return Err(MeshCacheError::InvalidMagic);
+        }
+
+        let (body, checksum) = bytes.split_at(bytes.len() - 4);
+        let expected = u32::from_le_bytes(checksum.try_into().unwrap());
+        let found = crc32(body);
+        if expected != found {
+            return Err(MeshCacheError::ChecksumMismatch { expected, found });
+        }
+
+        let mut reader = Reader {
+            bytes: body,
+            offset: MAGIC.len(),
+        };
+
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(MeshCacheError::UnsupportedVersion(version));
+        }
+
+        let topology_code = reader.u32()?;
+        let patch_vertices = reader.u32()?;
+        let topology = topology_from_code(topology_code, patch_vertices)
+            .ok_or(MeshCacheError::InvalidData("unknown topology"))?;
+
+        let vertex_stride = reader.u32()? as usize;
+        let vertex_count = reader.u32()? as usize;
+        let index_count = reader.u32()? as usize;
+        let attribute_count = reader.u32()? as usize;
+
+        let aabb = Aabb::new(reader.vec3()?, reader.vec3()?);
+        let bounding_sphere = BoundingSphere::new(reader.vec3()?, reader.f32()?);
+        reader.align();
+
+        let attributes = (0..attribute_count)
+            .map(|_| {
+                let location = reader.u32()?;
+                let components = reader.u32()?;
+                let component_type = component_type_from_code(reader.u32()?)
+                    .ok_or(MeshCacheError::InvalidData("unknown component type"))?;
+                let kind = kind_from_code(reader.u32()?)
+                    .ok_or(MeshCacheError::InvalidData("unknown attribute kind"))?;
+                let offset = reader.u32()? as usize;
+
+                let attribute = VertexAttribute {
+                    location,
+                    components,
+                    component_type,
+                    kind,
+                    offset,
+                };
+                if attribute.offset + attribute.byte_size() > vertex_stride {
+                    return Err(MeshCacheError::InvalidData("attribute outside of vertex"));
+                }
+
+                Ok(attribute)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        reader.align();
+
+        let vertex_bytes = reader.take(
+            vertex_stride
+                .checked_mul(vertex_count)
+                .ok_or(MeshCacheError::Truncated)?,
+        )?;
+        reader.align();
+        let index_bytes = reader.take(
+            index_count
+                .checked_mul(4)
+                .ok_or(MeshCacheError::Truncated)?,
+        )?;
+
+        // Out of range indices would make the GPU fetch past the vertex buffer.
+        if let Some(index) = index_bytes
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .find(|&index| index as usize >= vertex_count)
+        {
+            return Err(MeshCacheError::IndexOutOfRange {
+                index,
+                vertex_count,
+            });
+        }
+
+        Ok(Self {
+            attributes,
+            vertex_stride,
+            vertex_count,
+            topology,
+            aabb,
+            bounding_sphere,
+            vertex_bytes,
+            index_bytes,
+        })
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_bytes.len() / 4
+    }
+
+    pub fn indices(&self) -> Vec<u32> {
+        self.index_bytes
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect()
+    }
+
+    /// Whether the stored vertices can be uploaded directly as `V`s.
+    pub fn matches_format<V: VertexFormat>(&self) -> bool {
+        self.vertex_stride == std::mem::size_of::<V>()
+            && self.attributes.len() == V::ATTRIBUTES.len()
+            && V::ATTRIBUTES.iter().all(|a| self.attributes.contains(a))
+    }
+}
+
+/// Serialize a mesh into the cache format.
+pub fn to_bytes<V: VertexFormat>(
+    vertices: &[V],
+    indices: &[u32],
+    topology: PrimitiveTopology,
+) -> Vec<u8> {
+    fn pad(bytes: &mut Vec<u8>) {
+        bytes.resize(align(bytes.len()), 0);
+    }
+
+    fn put_vec3(bytes: &mut Vec<u8>, v: glam::Vec3) {
+        for c in v.to_array() {
+            bytes.extend_from_slice(&c.to_le_bytes());
+        }
+    }
+
+    let aabb = Aabb::from_points(vertices.iter().map(V::position));
+    let sphere = BoundingSphere::from_points(vertices.iter().map(V::position));
+    let (topology_code, patch_vertices) = topology_to_code(topology);
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&MAGIC);
+    for value in [
+        VERSION,
+        topology_code,
+        patch_vertices,
+        std::mem::size_of::<V>() as u32,
+        vertices.len() as u32,
+        indices.len() as u32,
+        V::ATTRIBUTES.len() as u32,
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    put_vec3(&mut bytes, aabb.min);
+    put_vec3(&mut bytes, aabb.max);
+    put_vec3(&mut bytes, sphere.center);
+    bytes.extend_from_slice(&sphere.radius.to_le_bytes());
+    pad(&mut bytes);
+    debug_assert_eq!(bytes.len(), HEADER_SIZE);
+
+    for attribute in V::ATTRIBUTES {
+        for value in [
+            attribute.location,
+            attribute.components,
+            component_type_to_code(attribute.component_type),
+            kind_to_code(attribute.kind),
+            attribute.offset as u32,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    debug_assert_eq!(
+        bytes.len(),
+        HEADER_SIZE + ATTRIBUTE_SIZE * V::ATTRIBUTES.len()
+    );
+    pad(&mut bytes);
+
+    // Copy attribute by attribute so that padding bytes are deterministic zeros.
+    let stride = std::mem::size_of::<V>();
+    for vertex in vertices {
+        let start = bytes.len();
+        bytes.resize(start + stride, 0);
+        for attribute in V::ATTRIBUTES {
+            let target = start + attribute.offset;
+            let target = &mut bytes[target..target + attribute.byte_size()];
+            copy_attribute(vertex, attribute, target);
+
+            if cfg!(target_endian = "big") {
+                for component in target.chunks_exact_mut(attribute.component_type.size()) {
+                    component.reverse();
+                }
+            }
+        }
+    }
+    pad(&mut bytes);
+
+    for index in indices {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+    pad(&mut bytes);
+
+    let checksum = crc32(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+
+    bytes
+}
+
+pub fn write<V: VertexFormat>(
+    path: impl AsRef<Path>,
+    vertices: &[V],
+    indices: &[u32],
+    topology: PrimitiveTopology,
+) -> Result<(), MeshCacheError> {
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(&to_bytes(vertices, indices, topology))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec2, vec3};
+
+    use super::*;
+    use crate::wrapper::vertex::VertexPNUV;
+
+    fn quad() -> (Vec<VertexPNUV>, Vec<u32>) {
+        let vertices = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)]
+            .into_iter()
+            .map(|(x, y)| VertexPNUV {
+                position: vec3(x, y, 0.5),
+                normal: vec3(0., 0., 1.),
+                uv: vec2(x, y) * 0.5 + 0.5,
+            })
+            .collect();
+        (vertices, vec![0, 1, 2, 2, 3, 0])
+    }
+
+    /// Replace the checksum after editing the body of a cache.
+    fn reseal(mut bytes: Vec<u8>) -> Vec<u8> {
+        let body = bytes.len() - 4;
+        let checksum = crc32(&bytes[..body]);
+        bytes[body..].copy_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let (vertices, indices) = quad();
+        let bytes = to_bytes(&vertices, &indices, PrimitiveTopology::Triangles);
+        assert_eq!(bytes.len() % 4, 0);
+
+        let cache = MeshCache::parse(&bytes).unwrap();
+        assert!(cache.matches_format::<VertexPNUV>());
+        assert_eq!(cache.topology, PrimitiveTopology::Triangles);
+        assert_eq!(cache.vertex_count, vertices.len());
+        assert_eq!(cache.indices(), indices);
+        assert_eq!(
+            cache.aabb,
+            Aabb::from_points(vertices.iter().map(VertexPNUV::position))
+        );
+
+        // Vertex blobs start aligned and hold the vertices attribute by attribute.
+        let offset = cache.vertex_bytes.as_ptr() as usize - bytes.as_ptr() as usize;
+        assert_eq!(offset % ALIGNMENT, 0);
+        for (vertex, stored) in vertices
+            .iter()
+            .zip(cache.vertex_bytes.chunks_exact(cache.vertex_stride))
+        {
+            let floats: Vec<f32> = stored
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect();
+            assert_eq!(floats[..3], vertex.position.to_array());
+            assert_eq!(floats[3..6], vertex.normal.to_array());
+            assert_eq!(floats[6..8], vertex.uv.to_array());
+        }
+    }
+
+    #[test]
+    fn corrupted_checksum_is_rejected() {
+        let (vertices, indices) = quad();
+        let mut bytes = to_bytes(&vertices, &indices, PrimitiveTopology::Triangles);
+        bytes[HEADER_SIZE] ^= 1;
+
+        assert!(matches!(
+            MeshCache::parse(&bytes),
+            Err(MeshCacheError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let (vertices, indices) = quad();
+        let bytes = to_bytes(&vertices, &indices, PrimitiveTopology::Triangles);
+
+        assert!(matches!(
+            MeshCache::parse(&bytes[..HEADER_SIZE]),
+            Err(MeshCacheError::Truncated)
+        ));
+
+        // A consistent checksum does not hide missing indices.
+        let cache = MeshCache::parse(&bytes).unwrap();
+        let indices_end = cache.index_bytes.as_ptr() as usize - bytes.as_ptr() as usize + 4;
+        let mut cut = bytes[..indices_end].to_vec();
+        cut.extend_from_slice(&[0; 4]);
+        let cut = reseal(cut);
+        assert!(matches!(
+            MeshCache::parse(&cut),
+            Err(MeshCacheError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn out_of_range_index_is_rejected() {
+        let (vertices, _) = quad();
+        let bytes = to_bytes(&vertices, &[0, 1, 4], PrimitiveTopology::Triangles);
+
+        assert!(matches!(
+            MeshCache::parse(&bytes),
+            Err(MeshCacheError::IndexOutOfRange {
+                index: 4,
+                vertex_count: 4
+            })
+        ));
+    }
+}
@@ -0,0 +1,882 @@
+use std::collections::HashMap;
+
+use glam::{Vec2, Vec3, Vec4};
+
+use super::{
+    simplify::simplify,
+    vertex::{Vertex, VertexFormat, VertexP, VertexPN, VertexPNUV, VertexPNUVT},
+};
+
+/// CPU-side indexed triangle list, in structure-of-arrays form.
+///
+/// Optional attributes are either empty or have one entry per position.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    /// Tangent with the bitangent sign in `w`.
+    pub tangents: Vec<Vec4>,
+    pub colors: Vec<Vec3>,
+    pub indices: Vec<u32>,
+}
+
+/// Index buffers of decreasing detail sharing the same vertices.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LodChain {
+    pub levels: Vec<LodLevel>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LodLevel {
+    pub indices: Vec<u32>,
+    /// Smallest projected size, as a fraction of the viewport height, at which this level is
+    /// still used.
+    pub min_screen_size: f32,
+}
+
+/// Vertex formats that can be built from `MeshData`.
+pub trait FromMeshData: VertexFormat {
+    fn from_mesh_data(data: &MeshData, index: usize) -> Self;
+}
+
+impl MeshData {
+    pub fn new(positions: Vec<Vec3>, indices: Vec<u32>) -> Self {
+        assert_eq!(indices.len() % 3, 0, "Index count must be a multiple of 3");
+
+        Self {
+            positions,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|tri| [tri[0] as usize, tri[1] as usize, tri[2] as usize])
+    }
+
+    pub fn to_vertices<V: FromMeshData>(&self) -> Vec<V> {
+        (0..self.vertex_count())
+            .map(|i| V::from_mesh_data(self, i))
+            .collect()
+    }
+
+    fn face_normal(&self, [a, b, c]: [usize; 3]) -> Vec3 {
+        let (a, b, c) = (self.positions[a], self.positions[b], self.positions[c]);
+        (b - a).cross(c - a)
+    }
+
+    /// Replace normals with area-weighted averages of the adjacent face normals.
+    pub fn compute_smooth_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.vertex_count()];
+
+        for tri in self.triangles() {
+            // Unnormalized, so larger faces weigh more.
+            let normal = self.face_normal(tri);
+            for i in tri {
+                normals[i] += normal;
+            }
+        }
+
+        self.normals = normals
+            .into_iter()
+            .map(|n| n.try_normalize().unwrap_or(Vec3::Y))
+            .collect();
+    }
+
+    /// Give every triangle its own vertices and set their normals to the face normal.
+    pub fn compute_flat_normals(&mut self) {
+        self.unweld();
+
+        let normals = self
+            .triangles()
+            .flat_map(|tri| [self.face_normal(tri).try_normalize().unwrap_or(Vec3::Y); 3])
+            .collect();
+
+        self.normals = normals;
+    }
+
+    /// Compute per-vertex tangents following the MikkTSpace conventions.
+    ///
+    /// Face tangents are projected onto each corner's normal and weighted by the corner angle,
+    /// and `w` holds the sign such that `bitangent = w * cross(normal, tangent)`. Like the
+    /// reference implementation, vertices shared by triangles of opposite handedness, such as
+    /// along mirrored UV seams, are split so that each side keeps its own frame.
+    pub fn compute_tangents(&mut self) {
+        assert!(
+            !self.uvs.is_empty(),
+            "Tangent generation requires texture coordinates"
+        );
+        if self.normals.is_empty() {
+            self.compute_smooth_normals();
+        }
+
+        self.split_mirrored_vertices();
+
+        let mut tangents = vec![Vec3::ZERO; self.vertex_count()];
+        let mut bitangents = vec![Vec3::ZERO; self.vertex_count()];
+
+        for tri in self.triangles() {
+            let [p0, p1, p2] = tri.map(|i| self.positions[i]);
+            let [uv0, uv1, uv2] = tri.map(|i| self.uvs[i]);
+
+            let (e1, e2) = (p1 - p0, p2 - p0);
+            let (d1, d2) = (uv1 - uv0, uv2 - uv0);
+
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() <= f32::EPSILON {
+                continue;
+            }
+            let r = det.recip();
+
+            let face_tangent = (e1 * d2.y - e2 * d1.y) * r;
+            let face_bitangent = (e2 * d1.x - e1 * d2.x) * r;
+
+            for corner in 0..3 {
+                let i = tri[corner];
+                let n = self.normals[i];
+                let project = |v: Vec3| (v - n * n.dot(v)).normalize_or_zero();
+
+                let p = self.positions[i];
+                let to_next = project(self.positions[tri[(corner + 1) % 3]] - p);
+                let to_prev = project(self.positions[tri[(corner + 2) % 3]] - p);
+                let angle = to_next.dot(to_prev).clamp(-1., 1.).acos();
+
+                tangents[i] += project(face_tangent) * angle;
+                bitangents[i] += project(face_bitangent) * angle;
+            }
+        }
+
+        self.tangents = (0..self.vertex_count())
+            .map(|i| {
+                let n = self.normals[i];
+                let t = (tangents[i] - n * n.dot(tangents[i]))
+                    .try_normalize()
+                    .unwrap_or_else(|| n.any_orthonormal_vector());
+                let sign = if n.cross(t).dot(bitangents[i]) < 0. {
+                    -1.
+                } else {
+                    1.
+                };
+                t.extend(sign)
+            })
+            .collect();
+    }
+
+    /// Whether the texture coordinates of `tri` are mirrored, or `None` if they are degenerate.
+    fn uv_mirrored(&self, tri: [usize; 3]) -> Option<bool> {
+        let [uv0, uv1, uv2] = tri.map(|i| self.uvs[i]);
+        let (d1, d2) = (uv1 - uv0, uv2 - uv0);
+
+        let det = d1.x * d2.y - d2.x * d1.y;
+        (det.abs() > f32::EPSILON).then_some(det < 0.)
+    }
+
+    /// Move the corners of triangles whose handedness differs from the first triangle using
+    /// their vertex to a copy of that vertex.
+    fn split_mirrored_vertices(&mut self) {
+        let mirrored = self
+            .triangles()
+            .map(|tri| self.uv_mirrored(tri))
+            .collect::<Vec<_>>();
+
+        let mut handedness = vec![None; self.vertex_count()];
+        let mut copies = HashMap::new();
+
+        for (t, mirrored) in mirrored.into_iter().enumerate() {
+            let Some(mirrored) = mirrored else {
+                continue;
+            };
+
+            for corner in t * 3..t * 3 + 3 {
+                let i = self.indices[corner] as usize;
+                if *handedness[i].get_or_insert(mirrored) != mirrored {
+                    self.indices[corner] =
+                        *copies.entry(i).or_insert_with(|| self.duplicate_vertex(i));
+                }
+            }
+        }
+    }
+
+    /// Append a copy of vertex `i`, returning its index.
+    fn duplicate_vertex(&mut self, i: usize) -> u32 {
+        fn duplicate_attribute<T: Copy>(attribute: &mut Vec<T>, i: usize) {
+            if !attribute.is_empty() {
+                attribute.push(attribute[i]);
+            }
+        }
+
+        duplicate_attribute(&mut self.positions, i);
+        duplicate_attribute(&mut self.normals, i);
+        duplicate_attribute(&mut self.uvs, i);
+        duplicate_attribute(&mut self.tangents, i);
+        duplicate_attribute(&mut self.colors, i);
+
+        (self.vertex_count() - 1) as u32
+    }
+
+    /// Merge vertices whose attributes all lie within `tolerance` of each other.
+    ///
+    /// Returns the number of vertices removed.
+    pub fn weld(&mut self, tolerance: f32) -> usize {
+        let cell_size = tolerance.max(f32::EPSILON) * 2.;
+        let cell_of = |p: Vec3| (p / cell_size).floor().as_ivec3();
+
+        let mut grid: HashMap<glam::IVec3, Vec<u32>> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.vertex_count());
+        let mut kept = Vec::new();
+
+        for i in 0..self.vertex_count() {
+            let cell = cell_of(self.positions[i]);
+
+            let existing = (-1..=1)
+                .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| (x, y, z))))
+                .filter_map(|(x, y, z)| grid.get(&(cell + glam::ivec3(x, y, z))))
+                .flatten()
+                .copied()
+                .find(|&j| self.vertices_match(i, j as usize, tolerance));
+
+            match existing {
+                Some(j) => remap.push(remap[j as usize]),
+                None => {
+                    remap.push(kept.len() as u32);
+                    grid.entry(cell).or_default().push(i as u32);
+                    kept.push(i);
+                }
+            }
+        }
+
+        let removed = self.vertex_count() - kept.len();
+        self.gather(&kept);
+        for index in self.indices.iter_mut() {
+            *index = remap[*index as usize];
+        }
+
+        removed
+    }
+
+    fn vertices_match(&self, a: usize, b: usize, tolerance: f32) -> bool {
+        fn close<T: Copy + std::ops::Sub<Output = T>>(
+            attribute: &[T],
+            a: usize,
+            b: usize,
+            length: impl Fn(T) -> f32,
+            tolerance: f32,
+        ) -> bool {
+            attribute.is_empty() || length(attribute[a] - attribute[b]) <= tolerance
+        }
+
+        close(&self.positions, a, b, Vec3::length, tolerance)
+            && close(&self.normals, a, b, Vec3::length, tolerance)
+            && close(&self.uvs, a, b, Vec2::length, tolerance)
+            && close(&self.tangents, a, b, Vec4::length, tolerance)
+            && close(&self.colors, a, b, Vec3::length, tolerance)
+    }
+
+    /// Remove triangles that reference the same vertex twice or whose area is at most
+    /// `area_epsilon`.
+    ///
+    /// Returns the number of triangles removed.
+    pub fn remove_degenerate_triangles(&mut self, area_epsilon: f32) -> usize {
+        let before = self.triangle_count();
+
+        let indices = self
+            .indices
+            .chunks_exact(3)
+            .filter(|tri| {
+                tri[0] != tri[1]
+                    && tri[1] != tri[2]
+                    && tri[2] != tri[0]
+                    && self
+                        .face_normal([tri[0] as usize, tri[1] as usize, tri[2] as usize])
+                        .length()
+                        / 2.
+                        > area_epsilon
+            })
+            .flatten()
+            .copied()
+            .collect();
+        self.indices = indices;
+
+        before - self.triangle_count()
+    }
+
+    /// Simplified indices with at most `target_index_count` entries, referring to the same
+    /// vertices. See [`simplify`].
+    pub fn simplify(&self, target_index_count: usize, max_error: f32) -> Vec<u32> {
+        simplify(
+            &self.positions,
+            &self.indices,
+            target_index_count,
+            max_error,
+        )
+    }
+
+    /// Build up to `levels` LODs, each keeping roughly `reduction` of the previous one's
+    /// triangles.
+    ///
+    /// Level `i` is used down to a projected size of `0.25 * reduction^i`, and the last level
+    /// is used at any size.
+    pub fn build_lod_chain(&self, levels: usize, reduction: f32, max_error: f32) -> LodChain {
+        let mut chain = vec![self.indices.clone()];
+
+        while chain.len() < levels {
+            let previous = chain.last().unwrap();
+            let target = ((previous.len() / 3) as f32 * reduction) as usize * 3;
+            let simplified = simplify(&self.positions, previous, target, max_error);
+
+            if simplified.is_empty() || simplified.len() >= previous.len() {
+                break;
+            }
+            chain.push(simplified);
+        }
+
+        let count = chain.len();
+        LodChain {
+            levels: chain
+                .into_iter()
+                .enumerate()
+                .map(|(i, indices)| LodLevel {
+                    indices,
+                    min_screen_size: if i + 1 == count {
+                        0.
+                    } else {
+                        0.25 * reduction.powi(i as i32)
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    /// Reorder triangles to maximize post-transform vertex cache hits, using Tom Forsyth's
+    /// linear-speed algorithm.
+    pub fn optimize_vertex_cache(&mut self) {
+        self.indices = forsyth::optimize(&self.indices, self.vertex_count());
+    }
+
+    /// Reorder clusters of triangles so that outward-facing ones are drawn first, reducing
+    /// overdraw while keeping most of the vertex cache locality.
+    ///
+    /// Should be run after `optimize_vertex_cache`.
+    pub fn optimize_overdraw(&mut self) {
+        const CACHE_SIZE: usize = 16;
+
+        if self.indices.is_empty() {
+            return;
+        }
+
+        // Split where the simulated cache has been completely flushed, as such boundaries can
+        // be moved around without adding misses.
+        let mut clusters: Vec<std::ops::Range<usize>> = Vec::new();
+        let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE);
+        let mut start = 0;
+        for (t, tri) in self.indices.chunks_exact(3).enumerate() {
+            let mut misses = 0;
+            for &index in tri {
+                if !cache.contains(&index) {
+                    misses += 1;
+                    if cache.len() == CACHE_SIZE {
+                        cache.remove(0);
+                    }
+                    cache.push(index);
+                }
+            }
+
+            if misses == 3 && t > start {
+                clusters.push(start..t);
+                start = t;
+            }
+        }
+        clusters.push(start..self.triangle_count());
+
+        let area_centroid = |tris: &mut dyn Iterator<Item = [usize; 3]>| {
+            let (mut centroid, mut normal, mut area) = (Vec3::ZERO, Vec3::ZERO, 0.);
+            for tri in tris {
+                let n = self.face_normal(tri);
+                let a = n.length();
+                centroid += tri.map(|i| self.positions[i]).iter().sum::<Vec3>() / 3. * a;
+                normal += n;
+                area += a;
+            }
+            (centroid / area.max(f32::EPSILON), normal)
+        };
+
+        let (mesh_centroid, _) = area_centroid(&mut self.triangles());
+
+        let mut keyed = clusters
+            .into_iter()
+            .map(|range| {
+                let (centroid, normal) =
+                    area_centroid(&mut self.triangles().skip(range.start).take(range.len()));
+                let key = (centroid - mesh_centroid).dot(normal.normalize_or_zero());
+                (key, range)
+            })
+            .collect::<Vec<_>>();
+        keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        self.indices = keyed
+            .into_iter()
+            .flat_map(|(_, range)| &self.indices[range.start * 3..range.end * 3])
+            .copied()
+            .collect();
+    }
+
+    /// Reorder vertices by first use in the index buffer and drop unreferenced ones.
+    pub fn optimize_vertex_fetch(&mut self) {
+        let mut remap = vec![u32::MAX; self.vertex_count()];
+        let mut order = Vec::with_capacity(self.vertex_count());
+
+        for index in self.indices.iter_mut() {
+            let slot = &mut remap[*index as usize];
+            if *slot == u32::MAX {
+                *slot = order.len() as u32;
+                order.push(*index as usize);
+            }
+            *index = *slot;
+        }
+
+        self.gather(&order);
+    }
+
+    /// Duplicate vertices so that every index refers to its own vertex.
+    fn unweld(&mut self) {
+        let order = self.indices.iter().map(|&i| i as usize).collect::<Vec<_>>();
+        self.gather(&order);
+        self.indices = (0..order.len() as u32).collect();
+    }
+
+    /// Keep only the vertices listed in `order`, in that order. Indices are left untouched.
+    fn gather(&mut self, order: &[usize]) {
+        fn gather_attribute<T: Copy>(attribute: &mut Vec<T>, order: &[usize]) {
+            if !attribute.is_empty() {
+                *attribute = order.iter().map(|&i| attribute[i]).collect();
+            }
+        }
+
+        gather_attribute(&mut self.positions, order);
+        gather_attribute(&mut self.normals, order);
+        gather_attribute(&mut self.uvs, order);
+        gather_attribute(&mut self.tangents, order);
+        gather_attribute(&mut self.colors, order);
+    }
+}
+
+mod forsyth {
+    const CACHE_SIZE: usize = 32;
+    const CACHE_DECAY_POWER: f32 = 1.5;
+    const LAST_TRI_SCORE: f32 = 0.75;
+    const VALENCE_BOOST_SCALE: f32 = 2.;
+    const VALENCE_BOOST_POWER: f32 = 0.5;
+
+    fn vertex_score(cache_position: Option<usize>, remaining_valence: usize) -> f32 {
+        if remaining_valence == 0 {
+            return -1.;
+        }
+
+        let cache_score = match cache_position {
+            None => 0.,
+            Some(position) if position < 3 => LAST_TRI_SCORE,
+            Some(position) => {
+                let scaler = 1. / (CACHE_SIZE - 3) as f32;
+                (1. - (position - 3) as f32 * scaler).powf(CACHE_DECAY_POWER)
+            }
+        };
+
+        cache_score + VALENCE_BOOST_SCALE * (remaining_valence as f32).powf(-VALENCE_BOOST_POWER)
+    }
+
+    pub(super) fn optimize(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+        let triangle_count = indices.len() / 3;
+
+        let mut adjacency = vec![Vec::new(); vertex_count];
+        for (t, tri) in indices.chunks_exact(3).enumerate() {
+            for &v in tri {
+                adjacency[v as usize].push(t);
+            }
+        }
+
+        let mut cache_position: Vec<Option<usize>> = vec![None; vertex_count];
+        let mut vertex_scores = adjacency
+            .iter()
+            .map(|tris| vertex_score(None, tris.len()))
+            .collect::<Vec<_>>();
+        let triangle_score = |t: usize, vertex_scores: &[f32]| -> f32 {
+            indices[t * 3..t * 3 + 3]
+                .iter()
+                .map(|&v| vertex_scores[v as usize])
+                .sum()
+        };
+        let mut triangle_scores = (0..triangle_count)
+            .map(|t| triangle_score(t, &vertex_scores))
+            .collect::<Vec<_>>();
+
+        let mut emitted = vec![false; triangle_count];
+        let mut output = Vec::with_capacity(indices.len());
+        let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+        let mut scan_cursor = 0;
+
+        for _ in 0..triangle_count {
+            // Prefer the best triangle touching the cache, fall back to the next unused one.
+            let best = cache
+                .iter()
+                .flat_map(|&v| adjacency[v as usize].iter().copied())
+                .max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]))
+                .unwrap_or_else(|| {
+                    while emitted[scan_cursor] {
+                        scan_cursor += 1;
+                    }
+                    scan_cursor
+                });
+
+            emitted[best] = true;
+            let tri = &indices[best * 3..best * 3 + 3];
+            output.extend_from_slice(tri);
+
+            for &v in tri {
+                adjacency[v as usize].retain(|&t| t != best);
+            }
+
+            let mut new_cache = tri.to_vec();
+            new_cache.extend(cache.iter().filter(|v| !tri.contains(v)));
+            let evicted = new_cache.split_off(new_cache.len().min(CACHE_SIZE));
+
+            for (position, &v) in new_cache.iter().enumerate() {
+                cache_position[v as usize] = Some(position);
+            }
+            for &v in evicted.iter() {
+                cache_position[v as usize] = None;
+            }
+
+            for &v in new_cache.iter().chain(evicted.iter()) {
+                let v = v as usize;
+                vertex_scores[v] = vertex_score(cache_position[v], adjacency[v].len());
+            }
+            for &v in new_cache.iter().chain(evicted.iter()) {
+                for &t in adjacency[v as usize].iter() {
+                    triangle_scores[t] = triangle_score(t, &vertex_scores);
+                }
+            }
+
+            cache = new_cache;
+        }
+
+        output
+    }
+}
+
+fn attribute_or<T: Copy>(attribute: &[T], index: usize, default: T) -> T {
+    attribute.get(index).copied().unwrap_or(default)
+}
+
+impl FromMeshData for Vertex {
+    fn from_mesh_data(data: &MeshData, index: usize) -> Self {
+        Self {
+            position: data.positions[index],
+            normal: attribute_or(&data.normals, index, Vec3::Y),
+            color: attribute_or(&data.colors, index, Vec3::ONE),
+        }
+    }
+}
+
+impl FromMeshData for VertexP {
+    fn from_mesh_data(data: &MeshData, index: usize) -> Self {
+        Self {
+            position: data.positions[index],
+        }
+    }
+}
+
+impl FromMeshData for VertexPN {
+    fn from_mesh_data(data: &MeshData, index: usize) -> Self {
+        Self {
+            position: data.positions[index],
+            normal: attribute_or(&data.normals, index, Vec3::Y),
+        }
+    }
+}
+
+impl FromMeshData for VertexPNUV {
+    fn from_mesh_data(data: &MeshData, index: usize) -> Self {
+        Self {
+            position: data.positions[index],
+            normal: attribute_or(&data.normals, index, Vec3::Y),
+            uv: attribute_or(&data.uvs, index, Vec2::ZERO),
+        }
+    }
+}
+
+impl FromMeshData for VertexPNUVT {
+    fn from_mesh_data(data: &MeshData, index: usize) -> Self {
+        Self {
+            position: data.positions[index],
+            normal: attribute_or(&data.normals, index, Vec3::Y),
+            uv: attribute_or(&data.uvs, index, Vec2::ZERO),
+            tangent: attribute_or(&data.tangents, index, Vec4::new(1., 0., 0., 1.)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec2, vec3};
+
+    use super::*;
+
+    /// Cube spanning [-1, 1], corner `i` having bit 0, 1 and 2 set for positive x, y and z.
+    fn cube() -> MeshData {
+        let positions = (0..8)
+            .map(|i| {
+                let bit = |b: u32| if i & (1 << b) != 0 { 1. } else { -1. };
+                vec3(bit(0), bit(1), bit(2))
+            })
+            .collect();
+        #[rustfmt::skip]
+        let indices = vec![
+            0, 4, 6, 0, 6, 2,
+            1, 3, 7, 1, 7, 5,
+            0, 1, 5, 0, 5, 4,
+            2, 6, 7, 2, 7, 3,
+            0, 2, 3, 0, 3, 1,
+            4, 5, 7, 4, 7, 6,
+        ];
+
+        MeshData::new(positions, indices)
+    }
+
+    /// `size` by `size` quads in the XY plane.
+    fn grid(size: u32) -> MeshData {
+        let positions = (0..=size)
+            .flat_map(|y| (0..=size).map(move |x| vec3(x as f32, y as f32, 0.)))
+            .collect();
+        let indices = (0..size)
+            .flat_map(|y| (0..size).map(move |x| y * (size + 1) + x))
+            .flat_map(|a| {
+                let (b, c) = (a + 1, a + size + 1);
+                [a, b, c + 1, a, c + 1, c]
+            })
+            .collect();
+
+        MeshData::new(positions, indices)
+    }
+
+    /// Triangles as sorted corner positions, each rotated to start at its smallest corner so
+    /// that winding is preserved.
+    fn triangle_set(data: &MeshData) -> Vec<[[i32; 3]; 3]> {
+        let mut triangles = data
+            .triangles()
+            .map(|tri| {
+                let mut corners = tri.map(|i| data.positions[i].as_ivec3().to_array());
+                let first = (0..3).min_by_key(|&c| corners[c]).unwrap();
+                corners.rotate_left(first);
+                corners
+            })
+            .collect::<Vec<_>>();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn flat_normals_follow_faces() {
+        let mut data = cube();
+        data.compute_flat_normals();
+
+        assert_eq!(data.vertex_count(), 36);
+        for tri in data.triangles() {
+            let center = tri.map(|i| data.positions[i]).iter().sum::<Vec3>() / 3.;
+            let face = center.round().as_ivec3();
+            for i in tri {
+                assert_eq!(data.normals[i].as_ivec3(), face);
+                assert_eq!(data.normals[i].length(), 1.);
+            }
+        }
+
+        // Corners of a face share a normal, other faces do not.
+        assert_eq!(data.weld(1e-4), 12);
+    }
+
+    #[test]
+    fn smooth_normals_point_out_of_corners() {
+        let mut data = cube();
+        data.compute_smooth_normals();
+
+        assert_eq!(data.vertex_count(), 8);
+        for (position, normal) in data.positions.iter().zip(&data.normals) {
+            assert!((normal.length() - 1.).abs() < 1e-6);
+            assert_eq!(normal.signum(), position.signum());
+            assert!(normal.dot(position.normalize()) > 0.9, "{normal}");
+        }
+    }
+
+    #[test]
+    fn weld_merges_within_tolerance() {
+        let epsilon = 1e-5;
+        let positions = vec![
+            vec3(0., 0., 0.),
+            vec3(1., 0., 0.),
+            vec3(0., 1., 0.),
+            vec3(1., epsilon, 0.),
+            vec3(1., 1., 0.),
+            vec3(-epsilon, 1., epsilon),
+        ];
+        let data = MeshData::new(positions, vec![0, 1, 2, 3, 4, 5]);
+
+        let mut strict = data.clone();
+        assert_eq!(strict.weld(epsilon / 10.), 0);
+        assert_eq!(strict, data);
+
+        let mut welded = data.clone();
+        assert_eq!(welded.weld(epsilon * 2.), 2);
+        assert_eq!(welded.vertex_count(), 4);
+        assert_eq!(welded.indices, vec![0, 1, 2, 1, 3, 2]);
+        assert_eq!(welded.positions[1], vec3(1., 0., 0.));
+    }
+
+    #[test]
+    fn weld_compares_every_attribute() {
+        let mut data = MeshData::new(vec![Vec3::ZERO; 3], vec![0, 1, 2]);
+        data.uvs = vec![vec2(0., 0.), vec2(0., 0.), vec2(1., 0.)];
+
+        assert_eq!(data.weld(1e-4), 1);
+        assert_eq!(data.indices, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn degenerate_triangles_are_removed() {
+        let positions = vec![
+            vec3(0., 0., 0.),
+            vec3(1., 0., 0.),
+            vec3(0., 1., 0.),
+            vec3(2., 0., 0.),
+            vec3(1., 1e-3, 0.),
+        ];
+        let mut data = MeshData::new(
+            positions,
+            vec![
+                0, 1, 2, // Regular, area 0.5.
+                0, 0, 2, // Repeated index.
+                0, 1, 3, // Collinear.
+                0, 3, 4, // Sliver, area 1e-3.
+            ],
+        );
+
+        let mut by_area = data.clone();
+        assert_eq!(by_area.remove_degenerate_triangles(0.01), 3);
+        assert_eq!(by_area.indices, vec![0, 1, 2]);
+
+        assert_eq!(data.remove_degenerate_triangles(0.), 2);
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 3, 4]);
+    }
+
+    #[test]
+    fn reorders_keep_triangles() {
+        let mut data = grid(8);
+        data.compute_smooth_normals();
+        let triangles = triangle_set(&data);
+
+        data.optimize_vertex_cache();
+        assert_eq!(triangle_set(&data), triangles);
+
+        data.optimize_overdraw();
+        assert_eq!(triangle_set(&data), triangles);
+
+        // An unreferenced vertex is dropped, and the others are numbered by first use.
+        data.positions.push(vec3(-1., -1., 0.));
+        data.normals.push(Vec3::Z);
+        data.optimize_vertex_fetch();
+        assert_eq!(data.vertex_count(), 81);
+        assert_eq!(data.normals.len(), 81);
+        assert_eq!(triangle_set(&data), triangles);
+
+        let mut next = 0;
+        for &index in &data.indices {
+            assert!(index <= next);
+            if index == next {
+                next += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn vertex_cache_order_reuses_vertices() {
+        let mut data = grid(16);
+        let misses = |indices: &[u32]| {
+            let mut cache = Vec::new();
+            let mut misses = 0;
+            for index in indices {
+                if !cache.contains(index) {
+                    misses += 1;
+                    cache.insert(0, *index);
+                    cache.truncate(16);
+                }
+            }
+            misses
+        };
+
+        // Scramble the triangles so that the grid order does not help.
+        let mut scrambled = data.indices.chunks_exact(3).collect::<Vec<_>>();
+        scrambled.sort_by_key(|tri| tri[0].wrapping_mul(2654435761) % 1009);
+        data.indices = scrambled.concat();
+        let before = misses(&data.indices);
+
+        data.optimize_vertex_cache();
+        assert!(misses(&data.indices) < before / 2);
+    }
+
+    #[test]
+    fn tangents_follow_uvs() {
+        let mut data = grid(2);
+        data.uvs = data.positions.iter().map(|p| p.truncate() * 0.5).collect();
+        data.compute_tangents();
+
+        assert_eq!(data.vertex_count(), 9);
+        assert_eq!(data.normals[4], Vec3::Z);
+        for tangent in &data.tangents {
+            assert!((*tangent - Vec4::new(1., 0., 0., 1.)).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn tangents_split_mirrored_seams() {
+        // Two triangles sharing the edge from 1 to 2, with UVs mirrored across it.
+        let positions = vec![
+            vec3(0., 0., 0.),
+            vec3(1., 0., 0.),
+            vec3(1., 1., 0.),
+            vec3(2., 0., 0.),
+        ];
+        let mut data = MeshData::new(positions, vec![0, 1, 2, 1, 3, 2]);
+        data.normals = vec![Vec3::Z; 4];
+        data.uvs = vec![vec2(0., 0.), vec2(1., 0.), vec2(1., 1.), vec2(0., 0.)];
+        data.compute_tangents();
+
+        assert_eq!(data.vertex_count(), 6);
+        assert_eq!(&data.indices[..3], &[0, 1, 2]);
+        assert_eq!(data.indices[4], 3);
+        for (corner, &index) in data.indices.iter().enumerate() {
+            let expected = if corner < 3 {
+                Vec4::new(1., 0., 0., 1.)
+            } else {
+                Vec4::new(-1., 0., 0., -1.)
+            };
+            let tangent = data.tangents[index as usize];
+            assert!((tangent - expected).length() < 1e-5, "{corner}: {tangent}");
+        }
+        assert_eq!(
+            data.indices
+                .iter()
+                .map(|&i| data.positions[i as usize])
+                .collect::<Vec<_>>()[3..],
+            [vec3(1., 0., 0.), vec3(2., 0., 0.), vec3(1., 1., 0.)]
+        );
+    }
+}
@@ -1,9 +1,27 @@
+pub mod bcn;
+pub mod bounds;
 pub mod buffer;
 pub mod camera;
+pub mod camera_path;
+pub mod container;
+pub mod controller;
+pub mod export;
+pub mod flipbook;
+pub mod framebuffer;
 pub mod handle;
 pub mod hash;
+pub mod image;
 pub mod material;
 pub mod mesh;
+pub mod mesh_cache;
+pub mod mesh_data;
+pub mod noise;
 pub mod program;
+pub mod sampler;
 pub mod scene;
+pub mod shadow;
+pub mod simplify;
 pub mod texture;
+pub mod vertex;
+pub mod view;
+pub mod watcher;
@@ -0,0 +1,588 @@
+//! Tileable noise for textures, generated on the CPU or with compute shaders.
+//!
+//! Positions are in tile coordinates, where the pattern repeats every unit. Both generators
+//! hash lattice cells the same way, so a seed gives the same pattern on either side up to
+//! float rounding. The CPU one is deterministic across runs and platforms.
+
+use glam::{IVec2, IVec3, UVec2, UVec3, Vec2, Vec3};
+
+use crate::dogl;
+
+use super::{
+    handle::AccessType,
+    hash::str_hash,
+    image::f32_to_f16,
+    program::Program,
+    texture::{ImageFormat, Texture, TextureData, TextureDimension},
+};
+
+/// Compute shader filling 2D images, relative to the working directory.
+pub const NOISE_2D_SHADER: &str = "shaders/noise/generate_2d.glsl";
+
+/// Compute shader filling 3D images, relative to the working directory.
+pub const NOISE_3D_SHADER: &str = "shaders/noise/generate_3d.glsl";
+
+/// Work group sizes of the 2D and 3D compute shaders along each axis.
+const GROUP_SIZE_2D: u32 = 8;
+const GROUP_SIZE_3D: u32 = 4;
+
+/// Step of the finite differences of the curl, in tile coordinates.
+const CURL_EPSILON: f32 = 1e-3;
+
+/// Seed offsets of the three potentials whose curl gives 3D flow.
+const CURL_SEEDS: [u32; 3] = [0, 0x5f35_6495, 0x9e37_79b9];
+
+/// Uniforms `NoiseGenerator` sets on both programs.
+const NOISE_UNIFORMS: [&str; 6] = [
+    "noise_kind",
+    "noise_output",
+    "noise_seed",
+    "noise_period",
+    "noise_octaves",
+    "noise_gain",
+];
+
+/// Bring the extremes of each kind close to [-1, 1].
+const SIMPLEX_2D_SCALE: f32 = 10.9;
+const SIMPLEX_3D_SCALE: f32 = 28.;
+
+const GRADIENTS_2D: [Vec2; 8] = {
+    use std::f32::consts::FRAC_1_SQRT_2 as D;
+    [
+        Vec2::new(1., 0.),
+        Vec2::new(-1., 0.),
+        Vec2::new(0., 1.),
+        Vec2::new(0., -1.),
+        Vec2::new(D, D),
+        Vec2::new(-D, D),
+        Vec2::new(D, -D),
+        Vec2::new(-D, -D),
+    ]
+};
+
+/// Cube edge midpoints, the gradients of improved Perlin noise.
+const GRADIENTS_3D: [Vec3; 12] = [
+    Vec3::new(1., 1., 0.),
+    Vec3::new(-1., 1., 0.),
+    Vec3::new(1., -1., 0.),
+    Vec3::new(-1., -1., 0.),
+    Vec3::new(1., 0., 1.),
+    Vec3::new(-1., 0., 1.),
+    Vec3::new(1., 0., -1.),
+    Vec3::new(-1., 0., -1.),
+    Vec3::new(0., 1., 1.),
+    Vec3::new(0., -1., 1.),
+    Vec3::new(0., 1., -1.),
+    Vec3::new(0., -1., -1.),
+];
+
+/// Values match the `NOISE_*` kind constants of `noise.glsl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Gradient noise on a square grid, in [-1, 1].
+    Perlin = 0,
+    /// Gradient noise on a simplex grid, in [-1, 1]. Tiling vertically in 2D needs an even
+    /// period.
+    Simplex = 1,
+    /// Distance to the closest of one random point per cell, mostly in [0, 1].
+    Worley = 2,
+}
+
+/// Values match the `NOISE_*` output constants of `noise.glsl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseOutput {
+    /// The fBm value itself, in every color channel.
+    Value = 0,
+    /// The curl of the fBm, a divergence-free flow for advecting particles or distorting
+    /// flames, in the color channels.
+    Curl = 1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoiseSettings {
+    pub kind: NoiseKind,
+    pub output: NoiseOutput,
+    pub seed: u32,
+    /// Number of lattice cells across the tile for the first octave.
+    pub period: u32,
+    /// Number of fBm octaves, each with twice the cells of the previous one.
+    pub octaves: u32,
+    /// Amplitude of each octave relative to the previous one.
+    pub gain: f32,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::Perlin,
+            output: NoiseOutput::Value,
+            seed: 0,
+            period: 4,
+            octaves: 1,
+            gain: 0.5,
+        }
+    }
+}
+
+/// Integer hash with good avalanche, the `lowbias32` mix.
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^ (x >> 16)
+}
+
+/// Hash of a lattice cell already wrapped into the tile.
+fn hash_cell(cell: IVec3, seed: u32) -> u32 {
+    hash(cell.x as u32 ^ hash(cell.y as u32 ^ hash(cell.z as u32 ^ hash(seed))))
+}
+
+/// Map a hash to [0, 1), exactly representable as a float.
+fn unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 / (1 << 24) as f32
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn perlin_2d(p: Vec2, period: i32, seed: u32) -> f32 {
+    let floor = p.floor();
+    let f = p - floor;
+    let cell = floor.as_ivec2();
+
+    let corner = |offset: IVec2| {
+        let wrapped = (cell + offset).rem_euclid(IVec2::splat(period));
+        let gradient = GRADIENTS_2D[(hash_cell(wrapped.extend(0), seed) & 7) as usize];
+        gradient.dot(f - offset.as_vec2())
+    };
+
+    let (u, v) = (fade(f.x), fade(f.y));
+    let bottom = lerp(corner(IVec2::new(0, 0)), corner(IVec2::new(1, 0)), u);
+    let top = lerp(corner(IVec2::new(0, 1)), corner(IVec2::new(1, 1)), u);
+    lerp(bottom, top, v) * std::f32::consts::SQRT_2
+}
+
+fn perlin_3d(p: Vec3, period: i32, seed: u32) -> f32 {
+    let floor = p.floor();
+    let f = p - floor;
+    let cell = floor.as_ivec3();
+
+    let corner = |x, y, z| {
+        let offset = IVec3::new(x, y, z);
+        let wrapped = (cell + offset).rem_euclid(IVec3::splat(period));
+        let gradient = GRADIENTS_3D[(hash_cell(wrapped, seed) % 12) as usize];
+        gradient.dot(f - offset.as_vec3())
+    };
+
+    let (u, v, w) = (fade(f.x), fade(f.y), fade(f.z));
+    let front = lerp(
+        lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+        lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+        v,
+    );
+    let back = lerp(
+        lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+        lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+        v,
+    );
+    lerp(front, back, w)
+}
+
+/// Simplex noise on the lattice of `psrdnoise`, whose vertices `(i.x - i.y / 2, i.y)` repeat
+/// horizontally every cell and vertically every two cells.
+fn simplex_2d(p: Vec2, period: i32, seed: u32) -> f32 {
+    let skewed = Vec2::new(p.x + p.y * 0.5, p.y);
+    let floor = skewed.floor();
+    let f = skewed - floor;
+    let base = floor.as_ivec2();
+    let middle = if f.x > f.y { IVec2::X } else { IVec2::Y };
+
+    let noise: f32 = [base, base + middle, base + IVec2::ONE]
+        .into_iter()
+        .map(|i| {
+            // Vertices are half-integer, their doubles wrap exactly.
+            let doubled = IVec2::new(2 * i.x - i.y, 2 * i.y);
+            let d = p - doubled.as_vec2() * 0.5;
+            let key = doubled.rem_euclid(IVec2::splat(2 * period));
+            let gradient = GRADIENTS_2D[(hash_cell(key.extend(0), seed) & 7) as usize];
+            let weight = (0.8 - d.length_squared()).max(0.);
+            weight.powi(4) * gradient.dot(d)
+        })
+        .sum();
+    noise * SIMPLEX_2D_SCALE
+}
+
+/// Simplex noise on the body-centered cubic lattice of `psrdnoise`, which repeats every cell
+/// along each axis.
+fn simplex_3d(p: Vec3, period: i32, seed: u32) -> f32 {
+    let skewed = Vec3::new(p.y + p.z, p.x + p.z, p.x + p.y);
+    let floor = skewed.floor();
+    let f = skewed - floor;
+    let base = floor.as_ivec3();
+
+    // Walk from the base corner along the axes by decreasing fractional part.
+    let largest = if f.x >= f.y && f.x >= f.z {
+        IVec3::X
+    } else if f.y >= f.z {
+        IVec3::Y
+    } else {
+        IVec3::Z
+    };
+    let smallest = if f.x < f.y && f.x < f.z {
+        IVec3::X
+    } else if f.y < f.z {
+        IVec3::Y
+    } else {
+        IVec3::Z
+    };
+
+    let noise: f32 = [
+        base,
+        base + largest,
+        base + IVec3::ONE - smallest,
+        base + IVec3::ONE,
+    ]
+    .into_iter()
+    .map(|i| {
+        let doubled = IVec3::new(-i.x + i.y + i.z, i.x - i.y + i.z, i.x + i.y - i.z);
+        let d = p - doubled.as_vec3() * 0.5;
+        let key = doubled.rem_euclid(IVec3::splat(2 * period));
+        let gradient = GRADIENTS_3D[(hash_cell(key, seed) % 12) as usize];
+        let weight = (0.5 - d.length_squared()).max(0.);
+        weight.powi(3) * gradient.dot(d)
+    })
+    .sum();
+    noise * SIMPLEX_3D_SCALE
+}
+
+fn worley_2d(p: Vec2, period: i32, seed: u32) -> f32 {
+    let cell = p.floor().as_ivec2();
+    let mut closest = f32::MAX;
+    for y in -1..=1 {
+        for x in -1..=1 {
+            let neighbor = cell + IVec2::new(x, y);
+            let h = hash_cell(neighbor.rem_euclid(IVec2::splat(period)).extend(0), seed);
+            let point = neighbor.as_vec2() + Vec2::new(unit(h), unit(hash(h)));
+            closest = closest.min(point.distance_squared(p));
+        }
+    }
+    closest.sqrt()
+}
+
+fn worley_3d(p: Vec3, period: i32, seed: u32) -> f32 {
+    let cell = p.floor().as_ivec3();
+    let mut closest = f32::MAX;
+    for z in -1..=1 {
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let neighbor = cell + IVec3::new(x, y, z);
+                let h = hash_cell(neighbor.rem_euclid(IVec3::splat(period)), seed);
+                let jitter = Vec3::new(unit(h), unit(hash(h)), unit(hash(hash(h))));
+                closest = closest.min((neighbor.as_vec3() + jitter).distance_squared(p));
+            }
+        }
+    }
+    closest.sqrt()
+}
+
+impl NoiseSettings {
+    /// Sum the octaves of `noise`, called with lattice positions, periods and seeds.
+    fn fbm<P: std::ops::Mul<f32, Output = P> + Copy>(
+        &self,
+        p: P,
+        seed: u32,
+        noise: impl Fn(P, i32, u32) -> f32,
+    ) -> f32 {
+        let mut sum = 0.;
+        let mut amplitude = 1.;
+        let mut total = 0.;
+        for octave in 0..self.octaves.max(1) {
+            let period = self.period << octave;
+            sum += amplitude * noise(p * period as f32, period as i32, seed.wrapping_add(octave));
+            total += amplitude;
+            amplitude *= self.gain;
+        }
+        sum / total
+    }
+
+    fn fbm_2d(&self, p: Vec2, seed: u32) -> f32 {
+        let noise = match self.kind {
+            NoiseKind::Perlin => perlin_2d,
+            NoiseKind::Simplex => simplex_2d,
+            NoiseKind::Worley => worley_2d,
+        };
+        self.fbm(p, seed, noise)
+    }
+
+    fn fbm_3d(&self, p: Vec3, seed: u32) -> f32 {
+        let noise = match self.kind {
+            NoiseKind::Perlin => perlin_3d,
+            NoiseKind::Simplex => simplex_3d,
+            NoiseKind::Worley => worley_3d,
+        };
+        self.fbm(p, seed, noise)
+    }
+
+    /// fBm value at `p`, normalized by the total amplitude of the octaves.
+    pub fn sample_2d(&self, p: Vec2) -> f32 {
+        self.fbm_2d(p, self.seed)
+    }
+
+    pub fn sample_3d(&self, p: Vec3) -> f32 {
+        self.fbm_3d(p, self.seed)
+    }
+
+    /// Curl of the fBm taken as a stream function, with distances in cells of the first
+    /// octave.
+    pub fn curl_2d(&self, p: Vec2) -> Vec2 {
+        let derivative = |axis: Vec2| {
+            let offset = axis * CURL_EPSILON;
+            (self.sample_2d(p + offset) - self.sample_2d(p - offset))
+                / (2. * CURL_EPSILON * self.period as f32)
+        };
+        Vec2::new(derivative(Vec2::Y), -derivative(Vec2::X))
+    }
+
+    /// Curl of a vector potential made of three fBm fields with different seeds, with
+    /// distances in cells of the first octave.
+    pub fn curl_3d(&self, p: Vec3) -> Vec3 {
+        // Partial derivative of potential component `i` along `axis`.
+        let derivative = |i: usize, axis: Vec3| {
+            let seed = self.seed.wrapping_add(CURL_SEEDS[i]);
+            let offset = axis * CURL_EPSILON;
+            (self.fbm_3d(p + offset, seed) - self.fbm_3d(p - offset, seed))
+                / (2. * CURL_EPSILON * self.period as f32)
+        };
+        Vec3::new(
+            derivative(2, Vec3::Y) - derivative(1, Vec3::Z),
+            derivative(0, Vec3::Z) - derivative(2, Vec3::X),
+            derivative(1, Vec3::X) - derivative(0, Vec3::Y),
+        )
+    }
+
+    fn texel_2d(&self, p: Vec2) -> [f32; 4] {
+        match self.output {
+            NoiseOutput::Value => {
+                let value = self.sample_2d(p);
+                [value, value, value, 1.]
+            }
+            NoiseOutput::Curl => {
+                let curl = self.curl_2d(p);
+                [curl.x, curl.y, 0., 1.]
+            }
+        }
+    }
+
+    fn texel_3d(&self, p: Vec3) -> [f32; 4] {
+        match self.output {
+            NoiseOutput::Value => {
+                let value = self.sample_3d(p);
+                [value, value, value, 1.]
+            }
+            NoiseOutput::Curl => self.curl_3d(p).extend(1.).to_array(),
+        }
+    }
+
+    /// Generate a tile of `size` pixels as `Rgba16Float`, sampling at pixel centers.
+    pub fn generate_2d(&self, size: UVec2) -> TextureData {
+        assert!(self.period > 0, "Noise period must be positive");
+
+        let pixels = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .flat_map(|pixel| self.texel_2d((pixel.as_vec2() + 0.5) / size.as_vec2()))
+            .flat_map(|value| f32_to_f16(value).to_ne_bytes())
+            .collect();
+        TextureData::new(pixels, size, ImageFormat::Rgba16Float)
+    }
+
+    /// Generate a tile of `size` voxels as `Rgba16Float` slices, for
+    /// `Texture::from_layers` with `TextureDimension::D3`.
+    pub fn generate_3d(&self, size: UVec3) -> Vec<TextureData> {
+        assert!(self.period > 0, "Noise period must be positive");
+
+        (0..size.z)
+            .map(|z| {
+                let pixels = (0..size.y)
+                    .flat_map(|y| (0..size.x).map(move |x| UVec3::new(x, y, z)))
+                    .flat_map(|voxel| self.texel_3d((voxel.as_vec3() + 0.5) / size.as_vec3()))
+                    .flat_map(|value| f32_to_f16(value).to_ne_bytes())
+                    .collect();
+                TextureData::new(pixels, size.truncate(), ImageFormat::Rgba16Float)
+            })
+            .collect()
+    }
+}
+
+/// Fills `Rgba16Float` textures with noise on the GPU, for sizes too large to generate on
+/// the CPU at load time.
+pub struct NoiseGenerator {
+    program_2d: Program,
+    program_3d: Program,
+}
+
+impl Default for NoiseGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoiseGenerator {
+    /// Compile `NOISE_2D_SHADER` and `NOISE_3D_SHADER`.
+    pub fn new() -> Self {
+        let generator = Self {
+            program_2d: Program::new_compute_shader(NOISE_2D_SHADER),
+            program_3d: Program::new_compute_shader(NOISE_3D_SHADER),
+        };
+
+        // Settings missing from a program would silently leave it with a period of 0.
+        for program in [&generator.program_2d, &generator.program_3d] {
+            for name in NOISE_UNIFORMS {
+                assert!(
+                    program.has_uniform(str_hash(name)),
+                    "Noise program lacks the {name} uniform"
+                );
+            }
+        }
+        generator
+    }
+
+    /// Generate a tile of `size` pixels with its mip chain.
+    pub fn generate_2d(&self, settings: &NoiseSettings, size: UVec2) -> Texture {
+        let texture = Texture::with_dimension(
+            size,
+            TextureDimension::D2,
+            Texture::mip_levels(size),
+            ImageFormat::Rgba16Float,
+        );
+        let groups = ((size + GROUP_SIZE_2D - 1) / GROUP_SIZE_2D).extend(1);
+        Self::run(&self.program_2d, settings, &texture, groups);
+        texture
+    }
+
+    /// Generate a tile of `size` voxels with its mip chain.
+    pub fn generate_3d(&self, settings: &NoiseSettings, size: UVec3) -> Texture {
+        let dimension = TextureDimension::D3(size.z);
+        let texture = Texture::with_dimension(
+            size.truncate(),
+            dimension,
+            Texture::mip_levels_for(size.truncate(), dimension),
+            ImageFormat::Rgba16Float,
+        );
+        let groups = (size + GROUP_SIZE_3D - 1) / GROUP_SIZE_3D;
+        Self::run(&self.program_3d, settings, &texture, groups);
+        texture
+    }
+
+    fn run(program: &Program, settings: &NoiseSettings, texture: &Texture, groups: UVec3) {
+        assert!(settings.period > 0, "Noise period must be positive");
+
+        program.set_uniform_u32(str_hash("noise_kind"), settings.kind as u32);
+        program.set_uniform_u32(str_hash("noise_output"), settings.output as u32);
+        program.set_uniform_u32(str_hash("noise_seed"), settings.seed);
+        program.set_uniform_u32(str_hash("noise_period"), settings.period);
+        program.set_uniform_u32(str_hash("noise_octaves"), settings.octaves);
+        program.set_uniform_f32(str_hash("noise_gain"), settings.gain);
+
+        texture.bind_as_image(0, AccessType::WriteOnly);
+        program.dispatch(groups);
+        unsafe {
+            dogl!(gl::MemoryBarrier(
+                gl::SHADER_IMAGE_ACCESS_BARRIER_BIT | gl::TEXTURE_FETCH_BARRIER_BIT
+            ));
+        }
+        texture.generate_mipmaps();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{uvec2, vec3};
+
+    use super::*;
+
+    /// Points spread over a few tiles, including negative coordinates.
+    fn points() -> impl Iterator<Item = Vec3> {
+        (0..200).map(|i| {
+            let t = i as f32;
+            vec3(
+                (t * 0.618).fract() * 3. - 1.,
+                (t * 0.414).fract() * 3. - 1.,
+                (t * 0.732).fract() * 3. - 1.,
+            )
+        })
+    }
+
+    /// Several octaves with an even period, which 2D simplex noise needs to tile vertically.
+    fn settings(kind: NoiseKind, seed: u32) -> NoiseSettings {
+        NoiseSettings {
+            kind,
+            seed,
+            period: 4,
+            octaves: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn noise_tiles_across_unit_offsets() {
+        for kind in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley] {
+            let settings = settings(kind, 7);
+            for p in points() {
+                let value = settings.sample_2d(p.truncate());
+                for axis in [Vec2::X, Vec2::Y] {
+                    let tiled = settings.sample_2d(p.truncate() + axis);
+                    assert!((tiled - value).abs() < 1e-4, "{kind:?} at {p} along {axis}");
+                }
+
+                let value = settings.sample_3d(p);
+                for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                    let tiled = settings.sample_3d(p + axis);
+                    assert!((tiled - value).abs() < 1e-4, "{kind:?} at {p} along {axis}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_are_deterministic() {
+        for kind in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley] {
+            let (a, b) = (settings(kind, 3), settings(kind, 4));
+            let mut differs = false;
+            for p in points() {
+                assert_eq!(a.sample_3d(p), settings(kind, 3).sample_3d(p));
+                differs |= a.sample_3d(p) != b.sample_3d(p);
+            }
+            assert!(differs, "{kind:?} ignores its seed");
+        }
+    }
+
+    #[test]
+    fn curl_2d_is_divergence_free() {
+        // Differences of the curl's own step sample the stream function at the same points
+        // for both terms, which cancel up to rounding.
+        let settings = NoiseSettings::default();
+        let e = CURL_EPSILON;
+        for p in points().map(|p| p.truncate()) {
+            let divergence = (settings.curl_2d(p + Vec2::X * e).x
+                - settings.curl_2d(p - Vec2::X * e).x
+                + settings.curl_2d(p + Vec2::Y * e).y
+                - settings.curl_2d(p - Vec2::Y * e).y)
+                / (2. * e);
+            assert!(divergence.abs() < 1e-3, "divergence {divergence} at {p}");
+        }
+    }
+
+    #[test]
+    fn generate_2d_fills_rgba16_float() {
+        let data = NoiseSettings::default().generate_2d(uvec2(16, 8));
+        assert_eq!(data.format(), ImageFormat::Rgba16Float);
+        assert_eq!(data.data().len(), 16 * 8 * 8);
+        assert_eq!(data.size(), uvec2(16, 8));
+    }
+}
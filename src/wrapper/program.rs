@@ -1,4 +1,7 @@
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
 
 use crate::{handle::GLHandle, hash::str_hash};
 use glam::Vec2;
@@ -171,6 +174,11 @@ impl Program {
         },
     }
 
+    /// Whether the program has an active uniform whose name hashes to `hash`.
+    pub(crate) fn has_uniform(&self, hash: u32) -> bool {
+        self.find_location(hash).is_some()
+    }
+
     fn find_location(&self, hash: u32) -> Option<u32> {
         self.uniform_locations
             .iter()
@@ -201,12 +209,7 @@ impl Program {
                 );
 
                 uniform_locations.push(UniformLocationInfo {
-                    hash: str_hash(
-                        name.into_iter()
-                            .map(char::from)
-                            .collect::<String>()
-                            .as_ref(),
-                    ),
+                    hash: uniform_name_hash(&name, len),
                     location: gl::GetUniformLocation(handle, &name as *const u8 as *const i8)
                         as u32,
                 });
@@ -224,11 +227,59 @@ impl Program {
             gl::UseProgram(self.handle.get());
         }
     }
+
+    /// Run a compute shader over `groups` work groups.
+    pub fn dispatch(&self, groups: glam::UVec3) {
+        assert!(self.is_compute, "Only compute shaders can be dispatched");
+
+        self.bind();
+        unsafe {
+            gl::DispatchCompute(groups.x, groups.y, groups.z);
+        }
+    }
 }
 
-fn load_shader(path: &str, r#type: gl::types::GLenum) -> u32 {
+/// Hash of a uniform name as `GetActiveUniform` writes it, `len` bytes followed by padding
+/// that must not take part in the hash.
+fn uniform_name_hash(name: &[u8], len: i32) -> u32 {
+    str_hash(&String::from_utf8_lossy(&name[..len as usize]))
+}
+
+/// Read a shader, replacing `#include "file"` lines with the contents of `file`, relative to the
+/// including shader. `#version` lines of included files are dropped.
+fn read_shader_source(path: &Path, include_stack: &mut Vec<PathBuf>) -> String {
     let code = std::fs::read_to_string(path).expect(format!("Couldn't open {path:?}").as_str());
 
+    if include_stack.iter().any(|p| p == path) {
+        panic!("Recursive include of {path:?}");
+    }
+    let is_included = !include_stack.is_empty();
+    include_stack.push(path.to_owned());
+
+    let mut source = String::with_capacity(code.len());
+    for (line_number, line) in code.lines().enumerate() {
+        let trimmed = line.trim_start();
+
+        if let Some(include) = trimmed.strip_prefix("#include") {
+            let file = include.trim().trim_matches('"');
+            let included = path.parent().unwrap_or(Path::new("")).join(file);
+
+            source += &read_shader_source(&included, include_stack);
+            // Keep compiler messages pointing at the right line of this file.
+            source += &format!("#line {}\n", line_number + 2);
+        } else if !(is_included && trimmed.starts_with("#version")) {
+            source += line;
+            source.push('\n');
+        }
+    }
+
+    include_stack.pop();
+    source
+}
+
+fn load_shader(path: &str, r#type: gl::types::GLenum) -> u32 {
+    let code = read_shader_source(Path::new(path), &mut Vec::new());
+
     unsafe {
         let shader = gl::CreateShader(r#type);
         assert_ne!(shader, 0);
@@ -320,3 +371,15 @@ fn check_program_error(program: u32) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_names_hash_without_padding() {
+        let mut name = [0u8; 1024];
+        name[..9].copy_from_slice(b"instanced");
+        assert_eq!(uniform_name_hash(&name, 9), str_hash("instanced"));
+    }
+}
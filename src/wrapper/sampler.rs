@@ -0,0 +1,274 @@
+use gl::types::{GLenum, GLuint};
+
+use crate::dogl;
+
+use super::handle::GLHandle;
+
+/// Core in GL 4.6 and with `ARB_texture_filter_anisotropic`, missing from the 4.5 bindings.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+/// Whether the current context supports anisotropic filtering, from its version or extensions.
+fn anisotropy_supported() -> bool {
+    let (mut major, mut minor, mut extension_count) = (0, 0, 0);
+    unsafe {
+        dogl!(gl::GetIntegerv(gl::MAJOR_VERSION, &mut major));
+        dogl!(gl::GetIntegerv(gl::MINOR_VERSION, &mut minor));
+    }
+    if (major, minor) >= (4, 6) {
+        return true;
+    }
+
+    unsafe {
+        dogl!(gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut extension_count));
+    }
+    (0..extension_count as GLuint).any(|i| {
+        let name = unsafe { std::ffi::CStr::from_ptr(gl::GetStringi(gl::EXTENSIONS, i).cast()) };
+        matches!(
+            name.to_bytes(),
+            b"GL_ARB_texture_filter_anisotropic" | b"GL_EXT_texture_filter_anisotropic"
+        )
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+/// How samples are blended between mip levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MipmapFilter {
+    /// Only the base level is sampled.
+    None,
+    Nearest,
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+    MirrorClampToEdge,
+}
+
+impl From<WrapMode> for GLenum {
+    fn from(value: WrapMode) -> Self {
+        match value {
+            WrapMode::Repeat => gl::REPEAT,
+            WrapMode::MirroredRepeat => gl::MIRRORED_REPEAT,
+            WrapMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+            WrapMode::ClampToBorder => gl::CLAMP_TO_BORDER,
+            WrapMode::MirrorClampToEdge => gl::MIRROR_CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// Comparison between a reference value and the sampled depth, for shadow samplers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareFunction {
+    Never,
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+    Always,
+}
+
+impl From<CompareFunction> for GLenum {
+    fn from(value: CompareFunction) -> Self {
+        match value {
+            CompareFunction::Never => gl::NEVER,
+            CompareFunction::Less => gl::LESS,
+            CompareFunction::LessEqual => gl::LEQUAL,
+            CompareFunction::Equal => gl::EQUAL,
+            CompareFunction::NotEqual => gl::NOTEQUAL,
+            CompareFunction::GreaterEqual => gl::GEQUAL,
+            CompareFunction::Greater => gl::GREATER,
+            CompareFunction::Always => gl::ALWAYS,
+        }
+    }
+}
+
+/// Sampling state, trilinear and repeating by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SamplerDescriptor {
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    pub mipmap_filter: MipmapFilter,
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+    pub wrap_w: WrapMode,
+    /// Color read outside of the texture with `WrapMode::ClampToBorder`.
+    pub border_color: glam::Vec4,
+    /// Clamped to what the driver supports, 1 disabling anisotropic filtering.
+    pub max_anisotropy: f32,
+    pub lod_bias: f32,
+    pub min_lod: f32,
+    pub max_lod: f32,
+    /// Turn depth texture samples into comparisons against a reference value.
+    pub compare: Option<CompareFunction>,
+}
+
+impl Default for SamplerDescriptor {
+    fn default() -> Self {
+        Self {
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: MipmapFilter::Linear,
+            wrap_u: WrapMode::Repeat,
+            wrap_v: WrapMode::Repeat,
+            wrap_w: WrapMode::Repeat,
+            border_color: glam::Vec4::ZERO,
+            max_anisotropy: 1.,
+            lod_bias: 0.,
+            min_lod: -1000.,
+            max_lod: 1000.,
+            compare: None,
+        }
+    }
+}
+
+impl SamplerDescriptor {
+    /// Unfiltered sampling, for pixel art and lookup tables.
+    pub fn nearest() -> Self {
+        Self {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_filter: MipmapFilter::Nearest,
+            ..Default::default()
+        }
+    }
+
+    /// Bilinear sampling of the base level, clamped to the edges.
+    pub fn clamped() -> Self {
+        Self {
+            mipmap_filter: MipmapFilter::None,
+            ..Default::default()
+        }
+        .with_wrap(WrapMode::ClampToEdge)
+    }
+
+    /// Filtered depth comparisons for `sampler2DShadow`-like samplers, lit when the reference
+    /// is closer to the light in reverse-Z.
+    pub fn shadow() -> Self {
+        Self {
+            compare: Some(CompareFunction::GreaterEqual),
+            ..Self::clamped()
+        }
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap_u = wrap;
+        self.wrap_v = wrap;
+        self.wrap_w = wrap;
+        self
+    }
+
+    pub fn with_anisotropy(mut self, max_anisotropy: f32) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+
+    fn min_filter_gl(&self) -> GLenum {
+        match (self.min_filter, self.mipmap_filter) {
+            (Filter::Nearest, MipmapFilter::None) => gl::NEAREST,
+            (Filter::Linear, MipmapFilter::None) => gl::LINEAR,
+            (Filter::Nearest, MipmapFilter::Nearest) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Linear, MipmapFilter::Nearest) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Nearest, MipmapFilter::Linear) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, MipmapFilter::Linear) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+}
+
+/// Sampling state bound alongside textures, overriding their own.
+pub struct Sampler {
+    handle: GLHandle,
+    descriptor: SamplerDescriptor,
+}
+
+impl Sampler {
+    pub fn new(descriptor: SamplerDescriptor) -> Self {
+        let mut handle: GLuint = 0;
+
+        unsafe {
+            dogl!(gl::CreateSamplers(1, &mut handle));
+
+            let int = |name: GLenum, value: GLenum| {
+                dogl!(gl::SamplerParameteri(handle, name, value as i32));
+            };
+            let float = |name: GLenum, value: f32| {
+                dogl!(gl::SamplerParameterf(handle, name, value));
+            };
+
+            int(gl::TEXTURE_MIN_FILTER, descriptor.min_filter_gl());
+            int(
+                gl::TEXTURE_MAG_FILTER,
+                match descriptor.mag_filter {
+                    Filter::Nearest => gl::NEAREST,
+                    Filter::Linear => gl::LINEAR,
+                },
+            );
+            int(gl::TEXTURE_WRAP_S, descriptor.wrap_u.into());
+            int(gl::TEXTURE_WRAP_T, descriptor.wrap_v.into());
+            int(gl::TEXTURE_WRAP_R, descriptor.wrap_w.into());
+
+            dogl!(gl::SamplerParameterfv(
+                handle,
+                gl::TEXTURE_BORDER_COLOR,
+                descriptor.border_color.as_ref().as_ptr()
+            ));
+
+            float(gl::TEXTURE_LOD_BIAS, descriptor.lod_bias);
+            float(gl::TEXTURE_MIN_LOD, descriptor.min_lod);
+            float(gl::TEXTURE_MAX_LOD, descriptor.max_lod);
+
+            // Without support, filtering stays isotropic.
+            if descriptor.max_anisotropy > 1. && anisotropy_supported() {
+                let mut supported = 1f32;
+                dogl!(gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut supported));
+                float(
+                    TEXTURE_MAX_ANISOTROPY,
+                    descriptor.max_anisotropy.min(supported),
+                );
+            }
+
+            match descriptor.compare {
+                Some(function) => {
+                    int(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE);
+                    int(gl::TEXTURE_COMPARE_FUNC, function.into());
+                }
+                None => int(gl::TEXTURE_COMPARE_MODE, gl::NONE),
+            }
+        }
+
+        Self {
+            handle: GLHandle::new(handle),
+            descriptor,
+        }
+    }
+
+    pub fn descriptor(&self) -> &SamplerDescriptor {
+        &self.descriptor
+    }
+
+    pub fn bind(&self, index: GLuint) {
+        unsafe {
+            gl::BindSampler(index, self.handle.get());
+        }
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSamplers(1, &self.handle.get());
+        }
+    }
+}
@@ -1,10 +1,21 @@
 use crate::{
-    buffer::GLBuffer, camera::Camera, hash, material::Material, mesh::StaticMesh, AsSlice,
+    bounds::{Aabb, BoundingSphere, Ray},
+    buffer::GLBuffer,
+    camera::Camera,
+    hash,
+    material::Material,
+    mesh::Mesh,
+    program::Program,
+    shadow::{CascadedShadowMap, ShadowData, ShadowSettings, SHADOW_DATA_BINDING, SHADOW_MAP_UNIT},
+    vertex::InstanceData,
+    view::View,
+    AsSlice,
 };
 
 use glrs::import;
 
 use std::{
+    cell::RefCell,
     fmt::Debug,
     rc::{Rc, Weak},
 };
@@ -19,31 +30,69 @@ pub struct TransformComponents {
     scale: glam::Vec3,
 }
 
+/// Number of objects sharing a mesh and material from which they are drawn instanced.
+const INSTANCING_THRESHOLD: usize = 2;
+
 pub struct SceneObject {
     pub transform: glam::Mat4,
-    mesh: Rc<StaticMesh>,
+    /// Per-object color, exposed to shaders as the `object_color` uniform or instance attribute.
+    pub color: glam::Vec4,
+    /// Per-object shader data, exposed as the `object_custom` uniform or instance attribute.
+    pub custom: glam::Vec4,
+    mesh: Rc<dyn Mesh>,
     material: Rc<Material>,
 }
 
 impl SceneObject {
-    pub fn new(mesh: Rc<StaticMesh>, material: Rc<Material>) -> Self {
+    pub fn new(mesh: Rc<dyn Mesh>, material: Rc<Material>) -> Self {
         Self {
             transform: glam::Mat4::default(),
+            color: glam::Vec4::ONE,
+            custom: glam::Vec4::ZERO,
             mesh,
             material,
         }
     }
 
-    fn render(&self) {
-        self.material
-            .program
-            .set_uniform_mat4(hash::str_hash("model"), &self.transform);
+    fn render(&self, camera: &Camera) {
+        let program = &self.material.program;
+        program.set_uniform_mat4(hash::str_hash("model"), &self.transform);
+        program.set_uniform_vec4(hash::str_hash("object_color"), self.color);
+        program.set_uniform_vec4(hash::str_hash("object_custom"), self.custom);
 
         self.material.bind();
-        self.mesh.draw();
+        self.mesh.draw_lod(self.select_lod(camera));
+    }
+
+    /// Level of detail to draw this object with from `camera`'s point of view.
+    pub fn select_lod(&self, camera: &Camera) -> usize {
+        if self.mesh.lod_count() <= 1 {
+            return 0;
+        }
+
+        self.mesh
+            .select_lod(camera.projected_size(&self.world_bounds()))
+    }
+
+    /// World-space bounding sphere.
+    pub fn world_bounds(&self) -> BoundingSphere {
+        self.mesh.bounds().transform(&self.transform)
+    }
+
+    /// World-space axis-aligned bounding box.
+    pub fn world_aabb(&self) -> Aabb {
+        self.mesh.aabb().transform(&self.transform)
+    }
+
+    pub fn instance_data(&self) -> InstanceData {
+        InstanceData {
+            transform: self.transform,
+            color: self.color,
+            custom: self.custom,
+        }
     }
 
-    pub fn mesh(&self) -> &Rc<StaticMesh> {
+    pub fn mesh(&self) -> &Rc<dyn Mesh> {
         &self.mesh
     }
 
@@ -62,9 +111,51 @@ impl SceneObject {
     }
 }
 
+/// Objects sharing a mesh and material.
+#[derive(Default)]
+struct ObjectGroup {
+    objects: Vec<SceneObject>,
+    /// Instance buffers of each level of detail, kept across frames and only reallocated
+    /// when they grow.
+    instance_buffers: RefCell<Vec<Option<GLBuffer<InstanceData>>>>,
+}
+
+impl ObjectGroup {
+    /// Draw objects sharing a mesh and material with one instanced draw per level of detail.
+    fn render_instanced(&self, objects: &[&SceneObject], camera: &Camera) {
+        let mesh = objects[0].mesh();
+
+        let mut lods = vec![Vec::new(); mesh.lod_count()];
+        for object in objects {
+            lods[object.select_lod(camera)].push(object.instance_data());
+        }
+
+        let mut buffers = self.instance_buffers.borrow_mut();
+        buffers.resize_with(lods.len(), || None);
+
+        for ((lod, instances), buffer) in lods.iter().enumerate().zip(buffers.iter_mut()) {
+            if instances.is_empty() {
+                continue;
+            }
+
+            let buffer = match buffer {
+                Some(buffer) if buffer.size() >= instances.len() => {
+                    buffer.update(0, instances);
+                    buffer
+                }
+                _ => buffer.insert(GLBuffer::new_dynamic(instances)),
+            };
+            mesh.draw_lod_instanced(lod, buffer, instances.len());
+        }
+    }
+}
+
+/// Objects sharing a material, grouped by mesh.
+type MeshGroups = WeakMap<Weak<dyn Mesh>, ObjectGroup>;
+
 #[derive(Default)]
 pub struct ObjectStorage {
-    inner: WeakMap<Weak<Material>, WeakMap<Weak<StaticMesh>, Vec<SceneObject>>>,
+    inner: WeakMap<Weak<Material>, MeshGroups>,
 }
 
 impl ObjectStorage {
@@ -73,22 +164,50 @@ impl ObjectStorage {
             .entry(object.material.clone())
             .or_insert_with(WeakMap::new)
             .entry(object.mesh.clone())
-            .or_insert_with(Vec::new)
+            .or_insert_with(ObjectGroup::default)
+            .objects
             .push(object);
     }
 
-    fn iter_material_groups(&self) -> impl Iterator<Item = &Vec<SceneObject>> {
+    fn iter_material_groups(&self) -> impl Iterator<Item = &ObjectGroup> {
         self.inner.values().flat_map(|map| map.values())
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &SceneObject> {
+        self.iter_material_groups()
+            .flat_map(|group| group.objects.iter())
+    }
+
     fn len(&self) -> usize {
-        self.inner
-            .values()
-            .flat_map(WeakMap::values)
-            .fold(0, |acc, vec| acc + vec.len())
+        self.iter_material_groups()
+            .fold(0, |acc, group| acc + group.objects.len())
     }
 }
 
+/// Per-frame object counts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub visible: usize,
+    pub culled: usize,
+}
+
+/// What `Scene::pick` tests rays against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PickMode {
+    /// World-space bounding spheres only.
+    #[default]
+    Bounds,
+    /// Triangles of meshes keeping pick geometry, bounding spheres for the others.
+    Triangles,
+}
+
+pub struct PickHit<'a> {
+    pub object: &'a SceneObject,
+    /// Distance along the ray, in multiples of its direction.
+    pub distance: f32,
+    pub point: glam::Vec3,
+}
+
 pub struct Scene {
     // point_ligts: Vec<PointLight>,
     pub objects: ObjectStorage,
@@ -97,6 +216,8 @@ pub struct Scene {
     pub sun_color: glam::Vec3,
 
     pub camera: Camera,
+
+    shadows: Option<CascadedShadowMap>,
 }
 
 impl Default for Scene {
@@ -106,6 +227,7 @@ impl Default for Scene {
             sun_direction: glam::vec3(1., 1., 1.),
             sun_color: glam::vec3(1., 1., 1.),
             camera: Default::default(),
+            shadows: None,
         }
     }
 }
@@ -124,6 +246,7 @@ impl Scene {
             sun_direction,
             sun_color,
             camera,
+            shadows: None,
         }
     }
 
@@ -131,9 +254,121 @@ impl Scene {
         self.objects.insert(object);
     }
 
-    pub fn render(&self) {
+    /// Make the sun cast shadows through cascaded shadow maps, replacing any previous ones.
+    pub fn enable_shadows(&mut self, settings: ShadowSettings) {
+        self.shadows = Some(CascadedShadowMap::new(settings));
+    }
+
+    pub fn disable_shadows(&mut self) {
+        self.shadows = None;
+    }
+
+    pub fn shadow_map(&self) -> Option<&CascadedShadowMap> {
+        self.shadows.as_ref()
+    }
+
+    /// Render every visible object with the scene's camera into the current framebuffer,
+    /// returning how many were drawn and culled.
+    pub fn render(&self) -> RenderStats {
+        let _shadow_data = self.render_shadows(&self.camera);
+        self.render_camera(&self.camera)
+    }
+
+    /// Render the scene through `view`, leaving the other parts of its target untouched.
+    pub fn render_view(&self, view: &View) -> RenderStats {
+        let _shadow_data = self.render_shadows(&view.camera);
+
+        view.begin();
+        let stats = self.render_camera(&view.camera);
+        view.end();
+        stats
+    }
+
+    /// Render the shadow cascades fitted to `camera` and bind them for the following draws,
+    /// restoring the current framebuffer and viewport. The returned buffer holds the bound
+    /// `ShadowData` and must outlive those draws.
+    fn render_shadows(&self, camera: &Camera) -> Option<GLBuffer<ShadowData>> {
+        let shadows = self.shadows.as_ref()?;
+
+        let casters = self
+            .objects
+            .iter()
+            .fold(Aabb::EMPTY, |acc, object| acc.union(&object.world_aabb()));
+        let cascades = shadows
+            .settings()
+            .cascades(camera, self.sun_direction, &casters);
+
+        let mut viewport = [0i32; 4];
+        let mut framebuffer = 0i32;
+        let (mut depth_func, mut depth_mask) = (0i32, 0u8);
+        let depth_test;
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut depth_func);
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut depth_mask);
+            depth_test = gl::IsEnabled(gl::DEPTH_TEST);
+            // Shaders sampling last frame's shadows must not read the layers being written.
+            gl::BindTextureUnit(SHADOW_MAP_UNIT, 0);
+        }
+
+        for (i, cascade) in cascades.iter().enumerate() {
+            shadows.begin_cascade(i, cascade);
+            self.render_casters(shadows.caster_program(), &cascade.camera(), camera);
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, framebuffer as u32);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl::DepthFunc(depth_func as u32);
+            gl::DepthMask(depth_mask);
+            if depth_test == gl::TRUE {
+                gl::Enable(gl::DEPTH_TEST);
+            } else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+        }
+
+        let buffer = GLBuffer::new(&[ShadowData::new(shadows.settings(), &cascades)]);
+        buffer.bind_to(BufferUsage::Uniform, SHADOW_DATA_BINDING);
+        shadows.bind(SHADOW_MAP_UNIT);
+
+        Some(buffer)
+    }
+
+    /// Draw the depth of every object inside `light_camera`'s view with the depth-only
+    /// `program`, using the levels of detail `camera` sees them at.
+    fn render_casters(&self, program: &Program, light_camera: &Camera, camera: &Camera) {
+        let frustum = light_camera.build_frustum();
+
+        for group in self.objects.iter_material_groups() {
+            let visible = group
+                .objects
+                .iter()
+                .filter(|object| frustum.intersects_sphere(&object.world_bounds()))
+                .collect::<Vec<_>>();
+
+            if visible.is_empty() {
+                continue;
+            }
+
+            let instanced = visible.len() >= INSTANCING_THRESHOLD;
+            program.set_uniform_u32(hash::str_hash("instanced"), instanced as u32);
+
+            if instanced {
+                group.render_instanced(&visible, camera);
+            } else {
+                for object in visible {
+                    program.set_uniform_mat4(hash::str_hash("model"), &object.transform);
+                    object.mesh.draw_lod(object.select_lod(camera));
+                }
+            }
+        }
+    }
+
+    fn render_camera(&self, camera: &Camera) -> RenderStats {
         let frame_data = FrameData {
-            view_proj: self.camera.view_proj,
+            view_proj: camera.view_proj,
             sun_dir: self.sun_direction.normalize(),
             sun_color: self.sun_color,
             ..Default::default()
@@ -142,12 +377,77 @@ impl Scene {
         let buffer = unsafe { GLBuffer::new(&[frame_data]) };
         buffer.bind_to(BufferUsage::Uniform, 0);
 
-        // TODO: Batch rendering
-        for vec in self.objects.iter_material_groups() {
-            vec[0].material().bind();
-            for object in vec.iter() {
-                object.render();
+        let frustum = camera.build_frustum();
+        let mut stats = RenderStats::default();
+
+        for group in self.objects.iter_material_groups() {
+            let visible = group
+                .objects
+                .iter()
+                .filter(|object| frustum.intersects_sphere(&object.world_bounds()))
+                .collect::<Vec<_>>();
+
+            stats.visible += visible.len();
+            stats.culled += group.objects.len() - visible.len();
+
+            if visible.is_empty() {
+                continue;
+            }
+
+            let material = visible[0].material();
+            material.bind();
+
+            let instanced = visible.len() >= INSTANCING_THRESHOLD;
+            material
+                .program
+                .set_uniform_u32(hash::str_hash("instanced"), instanced as u32);
+
+            if instanced {
+                group.render_instanced(&visible, camera);
+            } else {
+                for object in visible {
+                    object.render(camera);
+                }
             }
         }
+
+        stats
+    }
+
+    /// Nearest object hit by a world-space ray.
+    pub fn pick(&self, ray: &Ray, mode: PickMode) -> Option<PickHit<'_>> {
+        let mut nearest: Option<(&SceneObject, f32)> = None;
+
+        for object in self.objects.iter() {
+            let Some(sphere_distance) = object.world_bounds().intersect_ray(ray) else {
+                continue;
+            };
+            // Triangles lie inside the sphere, so they cannot be closer than its entry point.
+            if nearest.is_some_and(|(_, best)| sphere_distance >= best) {
+                continue;
+            }
+
+            let mesh = object.mesh();
+            let distance = if mode == PickMode::Triangles && mesh.has_pick_geometry() {
+                // Transforming the ray keeps distances along it unchanged.
+                let local_ray = ray.transform(&object.transform.inverse());
+                match mesh.raycast(&local_ray) {
+                    Some(distance) => distance,
+                    None => continue,
+                }
+            } else {
+                sphere_distance
+            };
+
+            if nearest.is_none_or(|(_, best)| distance < best) {
+                nearest = Some((object, distance));
+            }
+        }
+
+        nearest.map(|(object, distance)| PickHit {
+            object,
+            distance,
+            point: ray.at(distance),
+        })
     }
 }
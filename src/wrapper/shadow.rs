@@ -0,0 +1,312 @@
+use std::rc::Rc;
+
+use glam::{Mat4, Vec3};
+
+use crate::dogl;
+
+use super::{
+    bounds::Aabb,
+    camera::Camera,
+    handle::GLHandle,
+    hash::str_hash,
+    program::{Program, ShaderPaths},
+    sampler::{Sampler, SamplerDescriptor},
+    texture::{ImageFormat, Texture},
+};
+
+/// Highest cascade count, matching the size of the arrays in `ShadowData`.
+pub const MAX_CASCADES: usize = 4;
+
+/// Uniform buffer binding of `ShadowData`.
+pub const SHADOW_DATA_BINDING: u32 = 1;
+
+/// Texture unit the shadow map array is bound to while rendering.
+pub const SHADOW_MAP_UNIT: u32 = 15;
+
+/// Depth-only program drawing shadow casters, in place of their materials.
+pub const SHADOW_CASTER_VERTEX_SHADER: &str = "shaders/shadow/caster_vertex.glsl";
+pub const SHADOW_CASTER_FRAGMENT_SHADER: &str = "shaders/shadow/caster_fragment.glsl";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShadowSettings {
+    /// Number of cascades, at most `MAX_CASCADES`.
+    pub cascade_count: u32,
+    /// Width and height of each cascade's depth map.
+    pub resolution: u32,
+    /// Blend between uniform (0) and logarithmic (1) split distances.
+    pub split_lambda: f32,
+    /// View distance past which nothing is shadowed.
+    pub max_distance: f32,
+    /// Depth offset applied when comparing against the shadow map.
+    pub depth_bias: f32,
+    /// World-space offset along the surface normal, in shadow map texels.
+    pub normal_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            cascade_count: 4,
+            resolution: 2048,
+            split_lambda: 0.75,
+            max_distance: 50.,
+            depth_bias: 0.0005,
+            normal_bias: 1.5,
+        }
+    }
+}
+
+/// Light-space projection covering one slice of the view frustum.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cascade {
+    pub view: Mat4,
+    pub projection: Mat4,
+    /// View distance at which the slice ends.
+    pub far: f32,
+    /// World-space size of a shadow map texel.
+    pub texel_size: f32,
+}
+
+impl Cascade {
+    pub fn view_proj(&self) -> Mat4 {
+        self.projection * self.view
+    }
+
+    /// Camera rendering the cascade's depth map.
+    pub fn camera(&self) -> Camera {
+        let mut camera = Camera::new();
+        camera.set_proj(&self.projection);
+        camera.set_view(&self.view);
+        camera
+    }
+}
+
+impl ShadowSettings {
+    /// View distances splitting `[near, far]` into `cascade_count` slices, ending with `far`.
+    pub fn split_distances(&self, near: f32, far: f32) -> Vec<f32> {
+        let count = self.cascade_count as f32;
+        (1..=self.cascade_count)
+            .map(|i| {
+                let fraction = i as f32 / count;
+                let logarithmic = near * (far / near).powf(fraction);
+                let uniform = near + (far - near) * fraction;
+                uniform + (logarithmic - uniform) * self.split_lambda
+            })
+            .collect()
+    }
+
+    /// Fit one orthographic projection per slice of `camera`'s frustum, looking along
+    /// `-light_direction` and extended towards the light to keep every caster of `casters`.
+    ///
+    /// Each cascade encloses its slice in a sphere and snaps it to the texel grid, so that the
+    /// shadows do not shimmer as the camera moves or turns.
+    pub fn cascades(&self, camera: &Camera, light_direction: Vec3, casters: &Aabb) -> Vec<Cascade> {
+        assert!(
+            (1..=MAX_CASCADES as u32).contains(&self.cascade_count),
+            "Cascade count must be between 1 and {MAX_CASCADES}"
+        );
+
+        let near = camera.near();
+        let far = camera.far().min(self.max_distance);
+
+        let light_direction = light_direction.normalize();
+        let up = if light_direction.abs().y > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let light_view = Mat4::look_to_rh(Vec3::ZERO, -light_direction, up);
+
+        // Casters closest to the light, which looks down -z.
+        let casters_top = casters.transform(&light_view).max.z;
+
+        let inverse_view_proj = camera.view_proj.inverse();
+        let depth_at = |distance: f32| {
+            camera
+                .projection
+                .project_point3(Vec3::new(0., 0., -distance))
+                .z
+        };
+
+        let mut slice_near = near;
+        self.split_distances(near, far)
+            .into_iter()
+            .map(|slice_far| {
+                let corners = [depth_at(slice_near), depth_at(slice_far)].map(|z| {
+                    [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)]
+                        .map(|(x, y)| inverse_view_proj.project_point3(Vec3::new(x, y, z)))
+                });
+                slice_near = slice_far;
+
+                let corners = corners.concat();
+                let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+                // Distances to the centroid only depend on the slice's shape, so the radius is
+                // unaffected by camera rotation. Rounding absorbs float noise.
+                let radius = corners
+                    .iter()
+                    .map(|c| c.distance(center))
+                    .fold(0., f32::max);
+                let radius = (radius * 16.).ceil() / 16.;
+
+                let texel_size = 2. * radius / self.resolution as f32;
+                let light_center = light_view.transform_point3(center);
+                let snapped = (light_center.truncate() / texel_size).floor() * texel_size;
+
+                let z_far = -light_center.z + radius;
+                let z_near = (-light_center.z - radius).min(-casters_top);
+
+                let projection = Camera::orthographic_off_axis(
+                    snapped.x - radius,
+                    snapped.x + radius,
+                    snapped.y - radius,
+                    snapped.y + radius,
+                    z_near,
+                    z_far,
+                );
+
+                Cascade {
+                    view: light_view,
+                    projection,
+                    far: slice_far,
+                    texel_size,
+                }
+            })
+            .collect()
+    }
+}
+
+#[glrs::import(path = "shaders/structs.glsl")]
+#[derive(Default)]
+#[repr(C)]
+pub(crate) struct ShadowData;
+
+// Uploaded as a std140 uniform block, whose offsets the GLSL padding already matches.
+const _: () = {
+    assert!(std::mem::offset_of!(ShadowData, split_distances) == 256);
+    assert!(std::mem::offset_of!(ShadowData, texel_sizes) == 272);
+    assert!(std::mem::offset_of!(ShadowData, cascade_count) == 288);
+    assert!(std::mem::offset_of!(ShadowData, depth_bias) == 292);
+    assert!(std::mem::offset_of!(ShadowData, normal_bias) == 296);
+    assert!(std::mem::size_of::<ShadowData>() == 304);
+};
+
+impl ShadowData {
+    pub(crate) fn new(settings: &ShadowSettings, cascades: &[Cascade]) -> Self {
+        let mut data = ShadowData {
+            cascade_count: cascades.len() as u32,
+            depth_bias: settings.depth_bias,
+            normal_bias: settings.normal_bias,
+            ..Default::default()
+        };
+
+        for (i, cascade) in cascades.iter().enumerate() {
+            data.light_view_proj[i] = cascade.view_proj();
+            data.split_distances[i] = cascade.far;
+            data.texel_sizes[i] = cascade.texel_size;
+        }
+
+        data
+    }
+}
+
+/// Depth maps of the sun's cascades, stored as layers of a `Depth32Float` array.
+///
+/// Casters are drawn with a depth-only program reading positions and transforms, so vertex
+/// displacement done by material shaders does not show in the shadows.
+pub struct CascadedShadowMap {
+    settings: ShadowSettings,
+    texture: Rc<Texture>,
+    /// Hardware depth comparison for `sampler2DArrayShadow`.
+    sampler: Sampler,
+    framebuffer: GLHandle,
+    caster_program: Program,
+}
+
+impl CascadedShadowMap {
+    pub fn new(settings: ShadowSettings) -> Self {
+        let texture = Texture::new_array(
+            &glam::UVec2::splat(settings.resolution),
+            settings.cascade_count,
+            &ImageFormat::Depth32Float,
+        );
+
+        let mut framebuffer = 0;
+        unsafe {
+            dogl!(gl::CreateFramebuffers(1, &mut framebuffer));
+            dogl!(gl::NamedFramebufferDrawBuffer(framebuffer, gl::NONE));
+            dogl!(gl::NamedFramebufferReadBuffer(framebuffer, gl::NONE));
+        }
+
+        Self {
+            settings,
+            texture: Rc::new(texture),
+            sampler: Sampler::new(SamplerDescriptor::shadow()),
+            framebuffer: GLHandle::new(framebuffer),
+            caster_program: Program::new_shader(ShaderPaths {
+                vertex: SHADOW_CASTER_VERTEX_SHADER.to_string(),
+                geometry: None,
+                tess_control: None,
+                tess_evaluation: None,
+                fragment: SHADOW_CASTER_FRAGMENT_SHADER.to_string(),
+            }),
+        }
+    }
+
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    /// Bind the depth maps and their comparison sampler to `unit`.
+    pub(crate) fn bind(&self, unit: u32) {
+        self.texture.bind(unit);
+        self.sampler.bind(unit);
+    }
+
+    /// Program drawing casters into the cascade started by `begin_cascade`, reading the
+    /// `model` and `instanced` uniforms like material shaders.
+    pub(crate) fn caster_program(&self) -> &Program {
+        &self.caster_program
+    }
+
+    /// Bind the layer of `index` as the depth target with standard depth testing, clear it and
+    /// bind the caster program projecting through `cascade`.
+    pub(crate) fn begin_cascade(&self, index: usize, cascade: &Cascade) {
+        let resolution = self.settings.resolution as i32;
+
+        unsafe {
+            dogl!(gl::NamedFramebufferTextureLayer(
+                self.framebuffer.get(),
+                gl::DEPTH_ATTACHMENT,
+                self.texture.handle().get(),
+                0,
+                index as i32
+            ));
+            dogl!(gl::BindFramebuffer(
+                gl::DRAW_FRAMEBUFFER,
+                self.framebuffer.get()
+            ));
+            dogl!(gl::Viewport(0, 0, resolution, resolution));
+            // Casters must not depend on the depth state the last material left behind.
+            dogl!(gl::Enable(gl::DEPTH_TEST));
+            dogl!(gl::DepthFunc(gl::GEQUAL));
+            dogl!(gl::DepthMask(gl::TRUE));
+            dogl!(gl::Clear(gl::DEPTH_BUFFER_BIT));
+        }
+
+        self.caster_program.bind();
+        self.caster_program
+            .set_uniform_mat4(str_hash("light_view_proj"), &cascade.view_proj());
+    }
+}
+
+impl Drop for CascadedShadowMap {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer.get());
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+};
+
+use glam::{DVec3, Vec3};
+
+/// Extra weight given to the planes keeping open borders in place.
+const BOUNDARY_WEIGHT: f64 = 10.;
+
+/// Symmetric 4x4 error quadric, stored as its upper triangle.
+#[derive(Clone, Copy, Default)]
+struct Quadric {
+    a2: f64,
+    ab: f64,
+    ac: f64,
+    ad: f64,
+    b2: f64,
+    bc: f64,
+    bd: f64,
+    c2: f64,
+    cd: f64,
+    d2: f64,
+}
+
+impl Quadric {
+    fn from_plane(normal: DVec3, point: DVec3, weight: f64) -> Self {
+        let (a, b, c) = (normal.x, normal.y, normal.z);
+        let d = -normal.dot(point);
+
+        Self {
+            a2: a * a * weight,
+            ab: a * b * weight,
+            ac: a * c * weight,
+            ad: a * d * weight,
+            b2: b * b * weight,
+            bc: b * c * weight,
+            bd: b * d * weight,
+            c2: c * c * weight,
+            cd: c * d * weight,
+            d2: d * d * weight,
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.a2 += other.a2;
+        self.ab += other.ab;
+        self.ac += other.ac;
+        self.ad += other.ad;
+        self.b2 += other.b2;
+        self.bc += other.bc;
+        self.bd += other.bd;
+        self.c2 += other.c2;
+        self.cd += other.cd;
+        self.d2 += other.d2;
+    }
+
+    /// Weighted sum of squared distances from `p` to the accumulated planes.
+    fn error(&self, p: DVec3) -> f64 {
+        let (x, y, z) = (p.x, p.y, p.z);
+
+        (self.a2 * x * x + 2. * self.ab * x * y + 2. * self.ac * x * z + 2. * self.ad * x)
+            + (self.b2 * y * y + 2. * self.bc * y * z + 2. * self.bd * y)
+            + (self.c2 * z * z + 2. * self.cd * z + self.d2)
+    }
+}
+
+/// Candidate collapse of vertex `from` onto vertex `to`.
+#[derive(PartialEq)]
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cost.total_cmp(&other.cost)
+    }
+}
+
+/// Reduce a triangle list by collapsing edges in order of increasing quadric error.
+///
+/// Vertices are only ever collapsed onto other existing vertices, so the returned indices still
+/// refer to `positions` and LODs can share a single vertex buffer. Stops once the index count is
+/// at most `target_index_count` or the next collapse would move the surface by more than roughly
+/// `max_error`. Positions should be welded beforehand, as unshared vertices form borders that
+/// are preserved.
+pub fn simplify(
+    positions: &[Vec3],
+    indices: &[u32],
+    target_index_count: usize,
+    max_error: f32,
+) -> Vec<u32> {
+    let positions = positions.iter().map(|p| p.as_dvec3()).collect::<Vec<_>>();
+    let mut triangles = indices
+        .chunks_exact(3)
+        .map(|tri| [tri[0], tri[1], tri[2]])
+        .filter(|[a, b, c]| a != b && b != c && c != a)
+        .collect::<Vec<_>>();
+
+    let face_normal = |[a, b, c]: [u32; 3]| {
+        let (a, b, c) = (
+            positions[a as usize],
+            positions[b as usize],
+            positions[c as usize],
+        );
+        (b - a).cross(c - a)
+    };
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    let mut adjacency = vec![Vec::new(); positions.len()];
+    let mut edge_uses: HashMap<(u32, u32), (u32, usize)> = HashMap::new();
+
+    for (t, &tri) in triangles.iter().enumerate() {
+        let normal = face_normal(tri).normalize_or_zero();
+        let quadric = Quadric::from_plane(normal, positions[tri[0] as usize], 1.);
+
+        for (corner, &v) in tri.iter().enumerate() {
+            quadrics[v as usize].add(&quadric);
+            adjacency[v as usize].push(t);
+
+            let next = tri[(corner + 1) % 3];
+            edge_uses
+                .entry((v.min(next), v.max(next)))
+                .or_insert((0, t))
+                .0 += 1;
+        }
+    }
+
+    // Planes perpendicular to border edges, so that open borders do not shrink.
+    for (&(a, b), &(uses, t)) in edge_uses.iter() {
+        if uses != 1 {
+            continue;
+        }
+
+        let (pa, pb) = (positions[a as usize], positions[b as usize]);
+        let edge = pb - pa;
+        let normal = edge.cross(face_normal(triangles[t])).normalize_or_zero();
+        let quadric = Quadric::from_plane(normal, pa, BOUNDARY_WEIGHT * edge.length_squared());
+
+        quadrics[a as usize].add(&quadric);
+        quadrics[b as usize].add(&quadric);
+    }
+
+    let collapse_cost = |quadrics: &[Quadric], from: u32, to: u32| {
+        let mut q = quadrics[from as usize];
+        q.add(&quadrics[to as usize]);
+        q.error(positions[to as usize]).max(0.)
+    };
+
+    let mut heap = BinaryHeap::new();
+    for &(a, b) in edge_uses.keys() {
+        for (from, to) in [(a, b), (b, a)] {
+            heap.push(Reverse(Collapse {
+                cost: collapse_cost(&quadrics, from, to),
+                from,
+                to,
+            }));
+        }
+    }
+
+    let max_cost = (max_error as f64).powi(2);
+    let mut collapsed = vec![false; positions.len()];
+    let mut alive = vec![true; triangles.len()];
+    let mut alive_count = triangles.len();
+
+    while alive_count * 3 > target_index_count {
+        let Some(Reverse(Collapse { cost, from, to })) = heap.pop() else {
+            break;
+        };
+
+        if collapsed[from as usize] || collapsed[to as usize] {
+            continue;
+        }
+
+        // Quadrics only grow, so a stale entry can only underestimate the cost.
+        let current = collapse_cost(&quadrics, from, to);
+        if current > cost + f64::EPSILON {
+            heap.push(Reverse(Collapse {
+                cost: current,
+                from,
+                to,
+            }));
+            continue;
+        }
+        if current > max_cost {
+            break;
+        }
+
+        let still_connected = adjacency[from as usize]
+            .iter()
+            .any(|&t| alive[t] && triangles[t].contains(&to));
+        if !still_connected {
+            continue;
+        }
+
+        // Reject collapses that would fold a triangle over.
+        let flips = adjacency[from as usize].iter().any(|&t| {
+            let tri = triangles[t];
+            if !alive[t] || tri.contains(&to) {
+                return false;
+            }
+
+            let moved = tri.map(|v| if v == from { to } else { v });
+            let (before, after) = (face_normal(tri), face_normal(moved));
+            before.dot(after) <= 0. || after.length_squared() <= f64::EPSILON
+        });
+        if flips {
+            continue;
+        }
+
+        let from_quadric = quadrics[from as usize];
+        quadrics[to as usize].add(&from_quadric);
+        collapsed[from as usize] = true;
+
+        let moved_triangles = std::mem::take(&mut adjacency[from as usize]);
+        for t in moved_triangles {
+            if !alive[t] {
+                continue;
+            }
+
+            if triangles[t].contains(&to) {
+                alive[t] = false;
+                alive_count -= 1;
+            } else {
+                for v in triangles[t].iter_mut().filter(|v| **v == from) {
+                    *v = to;
+                }
+                adjacency[to as usize].push(t);
+            }
+        }
+
+        let mut neighbours = adjacency[to as usize]
+            .iter()
+            .filter(|&&t| alive[t])
+            .flat_map(|&t| triangles[t])
+            .filter(|&v| v != to)
+            .collect::<Vec<_>>();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+
+        for v in neighbours {
+            for (from, to) in [(v, to), (to, v)] {
+                heap.push(Reverse(Collapse {
+                    cost: collapse_cost(&quadrics, from, to),
+                    from,
+                    to,
+                }));
+            }
+        }
+    }
+
+    triangles
+        .iter()
+        .zip(alive)
+        .filter(|(_, alive)| *alive)
+        .flat_map(|(tri, _)| *tri)
+        .collect()
+}
@@ -1,26 +1,266 @@
-use stb_image::stb_image::stbi_load;
+use std::cell::Cell;
 
 use crate::handle::{AccessType, GLHandle};
 
+use super::{
+    bcn,
+    image::{TextureError, TextureSource},
+};
+
 use gl::types::{GLenum, GLuint};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ImageFormat {
+    /// Single channel masks.
+    R8Unorm,
+    Rg8Unorm,
     Rgba8Unorm,
     Rgba8SRgb,
 
     Rgb8Unorm,
     Rgb8SRgb,
 
-    Rgba16Float,
+    Rgba16Unorm,
+    /// 10 bits per color and 2 of alpha, more color precision than `Rgba8Unorm` for the
+    /// same size.
+    Rgb10A2Unorm,
 
+    R16Float,
+    /// Two channel vectors, such as flow maps.
+    Rg16Float,
+    Rgba16Float,
+    /// Single channel data such as density fields.
+    R32Float,
+    Rg32Float,
+    Rgba32Float,
+    /// Unsigned HDR color in 4 bytes, without alpha.
+    R11G11B10Float,
+
+    /// Integer formats, read with `usampler`/`isampler` and `uimage`/`iimage` in shaders and
+    /// without mipmaps.
+    R8Uint,
+    Rgba8Uint,
+    R32Uint,
+    R32Sint,
+    Rgba32Uint,
+
+    Depth16Unorm,
     Depth32Float,
+    Depth24Stencil8,
+    Depth32FloatStencil8,
+
+    /// BC1 (DXT1), RGB with 1-bit alpha in 8 bytes per 4x4 block.
+    Bc1RgbaUnorm,
+    Bc1RgbaSRgb,
+    /// BC3 (DXT5), RGB with smooth alpha in 16 bytes per block.
+    Bc3RgbaUnorm,
+    Bc3RgbaSRgb,
+    /// BC4, a single channel in 8 bytes per block.
+    Bc4RUnorm,
+    /// BC5, two channels in 16 bytes per block, typically normal maps.
+    Bc5RgUnorm,
+    /// BC7, high quality RGBA in 16 bytes per block.
+    Bc7RgbaUnorm,
+    Bc7RgbaSRgb,
 }
 
+/// How shaders read a format: `sampler`, `usampler` or `isampler` and likewise for images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleType {
+    /// Normalized, floating point and depth formats.
+    Float,
+    Uint,
+    Sint,
+}
+
+/// From `EXT_texture_compression_s3tc` and `EXT_texture_sRGB`, missing from the core bindings.
+const COMPRESSED_RGBA_S3TC_DXT1: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: GLenum = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: GLenum = 0x8C4F;
+
 impl ImageFormat {
     pub fn to_gl(&self) -> ImageFormatGL {
         ImageFormatGL::from(*self)
     }
+
+    /// Size of a pixel, panicking for block-compressed formats.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            ImageFormat::R8Unorm | ImageFormat::R8Uint => 1,
+            ImageFormat::Rg8Unorm | ImageFormat::R16Float | ImageFormat::Depth16Unorm => 2,
+            ImageFormat::Rgb8Unorm | ImageFormat::Rgb8SRgb => 3,
+            ImageFormat::Rgba8Unorm
+            | ImageFormat::Rgba8SRgb
+            | ImageFormat::Rgb10A2Unorm
+            | ImageFormat::Rg16Float
+            | ImageFormat::R32Float
+            | ImageFormat::R11G11B10Float
+            | ImageFormat::Rgba8Uint
+            | ImageFormat::R32Uint
+            | ImageFormat::R32Sint
+            | ImageFormat::Depth32Float
+            | ImageFormat::Depth24Stencil8 => 4,
+            ImageFormat::Rgba16Unorm
+            | ImageFormat::Rgba16Float
+            | ImageFormat::Rg32Float
+            | ImageFormat::Depth32FloatStencil8 => 8,
+            ImageFormat::Rgba32Float | ImageFormat::Rgba32Uint => 16,
+            _ => panic!("{self:?} is block-compressed"),
+        }
+    }
+
+    /// Number of channels, counting depth and stencil as one each.
+    pub fn channel_count(&self) -> u32 {
+        match self {
+            ImageFormat::R8Unorm
+            | ImageFormat::R16Float
+            | ImageFormat::R32Float
+            | ImageFormat::R8Uint
+            | ImageFormat::R32Uint
+            | ImageFormat::R32Sint
+            | ImageFormat::Depth16Unorm
+            | ImageFormat::Depth32Float
+            | ImageFormat::Bc4RUnorm => 1,
+            ImageFormat::Rg8Unorm
+            | ImageFormat::Rg16Float
+            | ImageFormat::Rg32Float
+            | ImageFormat::Depth24Stencil8
+            | ImageFormat::Depth32FloatStencil8
+            | ImageFormat::Bc5RgUnorm => 2,
+            ImageFormat::Rgb8Unorm | ImageFormat::Rgb8SRgb | ImageFormat::R11G11B10Float => 3,
+            ImageFormat::Rgba8Unorm
+            | ImageFormat::Rgba8SRgb
+            | ImageFormat::Rgba16Unorm
+            | ImageFormat::Rgb10A2Unorm
+            | ImageFormat::Rgba16Float
+            | ImageFormat::Rgba32Float
+            | ImageFormat::Rgba8Uint
+            | ImageFormat::Rgba32Uint
+            | ImageFormat::Bc1RgbaUnorm
+            | ImageFormat::Bc1RgbaSRgb
+            | ImageFormat::Bc3RgbaUnorm
+            | ImageFormat::Bc3RgbaSRgb
+            | ImageFormat::Bc7RgbaUnorm
+            | ImageFormat::Bc7RgbaSRgb => 4,
+        }
+    }
+
+    pub fn sample_type(&self) -> SampleType {
+        match self {
+            ImageFormat::R8Uint
+            | ImageFormat::Rgba8Uint
+            | ImageFormat::R32Uint
+            | ImageFormat::Rgba32Uint => SampleType::Uint,
+            ImageFormat::R32Sint => SampleType::Sint,
+            _ => SampleType::Float,
+        }
+    }
+
+    /// Whether values are read as integers rather than normalized or floating point.
+    pub fn is_integer(&self) -> bool {
+        self.sample_type() != SampleType::Float
+    }
+
+    /// Size of a 4x4 block of a block-compressed format.
+    pub fn block_bytes(&self) -> Option<usize> {
+        match self {
+            ImageFormat::Bc1RgbaUnorm | ImageFormat::Bc1RgbaSRgb | ImageFormat::Bc4RUnorm => {
+                Some(8)
+            }
+            ImageFormat::Bc3RgbaUnorm
+            | ImageFormat::Bc3RgbaSRgb
+            | ImageFormat::Bc5RgUnorm
+            | ImageFormat::Bc7RgbaUnorm
+            | ImageFormat::Bc7RgbaSRgb => Some(16),
+            _ => None,
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.block_bytes().is_some()
+    }
+
+    /// Bytes taken by an image of `size`, rounded up to whole blocks when compressed.
+    pub fn data_size(&self, size: glam::UVec2) -> usize {
+        match self.block_bytes() {
+            Some(block_bytes) => {
+                (size.x as usize).div_ceil(4) * (size.y as usize).div_ceil(4) * block_bytes
+            }
+            None => size.x as usize * size.y as usize * self.bytes_per_pixel(),
+        }
+    }
+
+    /// Whether color values are sRGB encoded, and decoded when sampled.
+    pub fn is_srgb(&self) -> bool {
+        matches!(
+            self,
+            ImageFormat::Rgba8SRgb
+                | ImageFormat::Rgb8SRgb
+                | ImageFormat::Bc1RgbaSRgb
+                | ImageFormat::Bc3RgbaSRgb
+                | ImageFormat::Bc7RgbaSRgb
+        )
+    }
+
+    pub fn is_depth(&self) -> bool {
+        matches!(
+            self,
+            ImageFormat::Depth16Unorm
+                | ImageFormat::Depth32Float
+                | ImageFormat::Depth24Stencil8
+                | ImageFormat::Depth32FloatStencil8
+        )
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        matches!(
+            self,
+            ImageFormat::Depth24Stencil8 | ImageFormat::Depth32FloatStencil8
+        )
+    }
+
+    /// Whether textures of this format can be bound as images for load and store, that is
+    /// have a GLSL image format qualifier. Three channel, sRGB, depth and compressed formats
+    /// cannot.
+    pub fn supports_image_load_store(&self) -> bool {
+        !(matches!(self, ImageFormat::Rgb8Unorm)
+            || self.is_srgb()
+            || self.is_depth()
+            || self.is_compressed())
+    }
+
+    /// Whether mipmaps can be generated from the base level.
+    pub fn supports_mipmap_generation(&self) -> bool {
+        !(self.is_compressed() || self.is_integer())
+    }
+
+    /// Whether the driver can sample textures of this format and dimension. S3TC is an
+    /// extension some drivers leave out, and block compression of volumes is rarely supported.
+    pub fn is_supported(&self, dimension: TextureDimension) -> bool {
+        let mut supported = 0;
+        unsafe {
+            gl::GetInternalformativ(
+                dimension.target(),
+                self.to_gl().internal_format,
+                gl::INTERNALFORMAT_SUPPORTED,
+                1,
+                &mut supported,
+            );
+        }
+        supported == gl::TRUE as i32
+    }
+
+    /// Uncompressed format the CPU decoders of a block-compressed format produce.
+    pub fn decompressed(&self) -> ImageFormat {
+        match self {
+            ImageFormat::Bc1RgbaSRgb | ImageFormat::Bc3RgbaSRgb | ImageFormat::Bc7RgbaSRgb => {
+                ImageFormat::Rgba8SRgb
+            }
+            format if format.is_compressed() => ImageFormat::Rgba8Unorm,
+            format => *format,
+        }
+    }
 }
 
 pub struct ImageFormatGL {
@@ -29,18 +269,70 @@ pub struct ImageFormatGL {
     component_type: GLenum,
 }
 
+impl ImageFormatGL {
+    pub fn internal_format(&self) -> GLenum {
+        self.internal_format
+    }
+}
+
 impl From<ImageFormat> for ImageFormatGL {
     fn from(format: ImageFormat) -> Self {
         let (format, internal_format, component_type) = match format {
+            ImageFormat::R8Unorm => (gl::RED, gl::R8, gl::UNSIGNED_BYTE),
+            ImageFormat::Rg8Unorm => (gl::RG, gl::RG8, gl::UNSIGNED_BYTE),
             ImageFormat::Rgba8Unorm => (gl::RGBA, gl::RGBA8, gl::UNSIGNED_BYTE),
             ImageFormat::Rgba8SRgb => (gl::RGBA, gl::SRGB8_ALPHA8, gl::UNSIGNED_BYTE),
 
             ImageFormat::Rgb8Unorm => (gl::RGB, gl::RGB8, gl::UNSIGNED_BYTE),
             ImageFormat::Rgb8SRgb => (gl::RGB, gl::SRGB8, gl::UNSIGNED_BYTE),
 
-            ImageFormat::Rgba16Float => (gl::RGBA, gl::RGBA16F, gl::FLOAT),
-
+            ImageFormat::Rgba16Unorm => (gl::RGBA, gl::RGBA16, gl::UNSIGNED_SHORT),
+            ImageFormat::Rgb10A2Unorm => (gl::RGBA, gl::RGB10_A2, gl::UNSIGNED_INT_2_10_10_10_REV),
+
+            ImageFormat::R16Float => (gl::RED, gl::R16F, gl::HALF_FLOAT),
+            ImageFormat::Rg16Float => (gl::RG, gl::RG16F, gl::HALF_FLOAT),
+            ImageFormat::Rgba16Float => (gl::RGBA, gl::RGBA16F, gl::HALF_FLOAT),
+            ImageFormat::R32Float => (gl::RED, gl::R32F, gl::FLOAT),
+            ImageFormat::Rg32Float => (gl::RG, gl::RG32F, gl::FLOAT),
+            ImageFormat::Rgba32Float => (gl::RGBA, gl::RGBA32F, gl::FLOAT),
+            ImageFormat::R11G11B10Float => (
+                gl::RGB,
+                gl::R11F_G11F_B10F,
+                gl::UNSIGNED_INT_10F_11F_11F_REV,
+            ),
+
+            ImageFormat::R8Uint => (gl::RED_INTEGER, gl::R8UI, gl::UNSIGNED_BYTE),
+            ImageFormat::Rgba8Uint => (gl::RGBA_INTEGER, gl::RGBA8UI, gl::UNSIGNED_BYTE),
+            ImageFormat::R32Uint => (gl::RED_INTEGER, gl::R32UI, gl::UNSIGNED_INT),
+            ImageFormat::R32Sint => (gl::RED_INTEGER, gl::R32I, gl::INT),
+            ImageFormat::Rgba32Uint => (gl::RGBA_INTEGER, gl::RGBA32UI, gl::UNSIGNED_INT),
+
+            ImageFormat::Depth16Unorm => (
+                gl::DEPTH_COMPONENT,
+                gl::DEPTH_COMPONENT16,
+                gl::UNSIGNED_SHORT,
+            ),
             ImageFormat::Depth32Float => (gl::DEPTH_COMPONENT, gl::DEPTH_COMPONENT32F, gl::FLOAT),
+            ImageFormat::Depth24Stencil8 => (
+                gl::DEPTH_STENCIL,
+                gl::DEPTH24_STENCIL8,
+                gl::UNSIGNED_INT_24_8,
+            ),
+            ImageFormat::Depth32FloatStencil8 => (
+                gl::DEPTH_STENCIL,
+                gl::DEPTH32F_STENCIL8,
+                gl::FLOAT_32_UNSIGNED_INT_24_8_REV,
+            ),
+
+            // Compressed data is uploaded by internal format alone.
+            ImageFormat::Bc1RgbaUnorm => (gl::NONE, COMPRESSED_RGBA_S3TC_DXT1, gl::NONE),
+            ImageFormat::Bc1RgbaSRgb => (gl::NONE, COMPRESSED_SRGB_ALPHA_S3TC_DXT1, gl::NONE),
+            ImageFormat::Bc3RgbaUnorm => (gl::NONE, COMPRESSED_RGBA_S3TC_DXT5, gl::NONE),
+            ImageFormat::Bc3RgbaSRgb => (gl::NONE, COMPRESSED_SRGB_ALPHA_S3TC_DXT5, gl::NONE),
+            ImageFormat::Bc4RUnorm => (gl::NONE, gl::COMPRESSED_RED_RGTC1, gl::NONE),
+            ImageFormat::Bc5RgUnorm => (gl::NONE, gl::COMPRESSED_RG_RGTC2, gl::NONE),
+            ImageFormat::Bc7RgbaUnorm => (gl::NONE, gl::COMPRESSED_RGBA_BPTC_UNORM, gl::NONE),
+            ImageFormat::Bc7RgbaSRgb => (gl::NONE, gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM, gl::NONE),
         };
 
         ImageFormatGL {
@@ -52,112 +344,548 @@ impl From<ImageFormat> for ImageFormatGL {
 }
 
 pub struct TextureData {
-    data: Vec<u8>,
+    /// Mip chain, starting with the full size image.
+    levels: Vec<Vec<u8>>,
     size: glam::UVec2,
     format: ImageFormat,
+    source: Option<TextureSource>,
 }
 
 impl TextureData {
-    pub fn from_file(path: &str) -> Option<TextureData> {
-        let mut width = 0;
-        let mut height = 0;
-        let mut channels = 0;
-
-        let bytes = unsafe {
-            let img = stbi_load(
-                path.as_ptr() as *const std::os::raw::c_char,
-                &mut width,
-                &mut height,
-                &mut channels,
-                4,
+    /// Wrap tightly packed pixels, rows going from the first to the last. Mipmaps are
+    /// generated on upload.
+    pub fn new(data: Vec<u8>, size: glam::UVec2, format: ImageFormat) -> Self {
+        Self::with_mips(vec![data], size, format)
+    }
+
+    /// Wrap a pre-built mip chain, each level half the size of the previous one.
+    pub fn with_mips(levels: Vec<Vec<u8>>, size: glam::UVec2, format: ImageFormat) -> Self {
+        assert!(!levels.is_empty(), "Texture data has no levels");
+        assert!(
+            levels.len() <= Texture::mip_levels(size) as usize,
+            "Too many mip levels for a {}x{} image",
+            size.x,
+            size.y
+        );
+
+        for (level, data) in levels.iter().enumerate() {
+            let level_size = mip_size(size, level as u32);
+            assert_eq!(
+                data.len(),
+                format.data_size(level_size),
+                "Level {level} does not match a {}x{} {format:?} image",
+                level_size.x,
+                level_size.y
             );
-            if (std::ptr::null() == img) {
-                return None;
-            }
-            std::slice::from_raw_parts(img, (width * height * channels) as usize).to_vec()
-        };
+        }
+
+        Self {
+            levels,
+            size,
+            format,
+            source: None,
+        }
+    }
+
+    /// Pixels of the full size image.
+    pub fn data(&self) -> &[u8] {
+        &self.levels[0]
+    }
+
+    pub fn levels(&self) -> &[Vec<u8>] {
+        &self.levels
+    }
+
+    pub fn size(&self) -> glam::UVec2 {
+        self.size
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// File the data was loaded from, if any.
+    pub fn source(&self) -> Option<&TextureSource> {
+        self.source.as_ref()
+    }
+
+    pub(crate) fn set_source(&mut self, source: TextureSource) {
+        self.source = Some(source);
+    }
+
+    /// Reverse the order of the rows of every level, turning GL's bottom-up images top-down
+    /// and back.
+    pub fn flip_vertically(&mut self) {
+        assert!(
+            !self.format.is_compressed(),
+            "Cannot flip {:?} images",
+            self.format
+        );
+
+        for (level, data) in self.levels.iter_mut().enumerate() {
+            let size = mip_size(self.size, level as u32);
+            let row = size.x as usize * self.format.bytes_per_pixel();
+            *data = data.chunks_exact(row).rev().flatten().copied().collect();
+        }
+    }
 
-        if (width <= 0 || height <= 0 || channels <= 0) {
-            return None;
+    /// Decode block-compressed data to RGBA8, keeping the mip chain.
+    pub fn decompress(&self) -> TextureData {
+        if !self.format.is_compressed() {
+            return Self::with_mips(self.levels.clone(), self.size, self.format);
         }
 
-        Some(TextureData {
-            data: bytes,
-            size: glam::uvec2(width as u32, height as u32),
-            format: ImageFormat::Rgba8Unorm,
-        })
+        let levels = self
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                bcn::decompress(self.format, data, mip_size(self.size, level as u32))
+            })
+            .collect();
+
+        Self::with_mips(levels, self.size, self.format.decompressed())
+    }
+}
+
+/// Size of mip `level` of an image of `size`.
+pub fn mip_size(size: glam::UVec2, level: u32) -> glam::UVec2 {
+    (size >> level).max(glam::UVec2::ONE)
+}
+
+/// Shape of a texture and how its layers are addressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureDimension {
+    D2,
+    /// Array of 2D images, such as flipbooks or shadow cascades.
+    D2Array(u32),
+    /// Six square faces in the +X, -X, +Y, -Y, +Z, -Z order.
+    Cube,
+    /// Array of cubemaps, with six consecutive layers per cubemap.
+    CubeArray(u32),
+    /// Volume of the given depth, its slices being layers.
+    D3(u32),
+}
+
+impl TextureDimension {
+    pub fn target(&self) -> GLenum {
+        match self {
+            TextureDimension::D2 => gl::TEXTURE_2D,
+            TextureDimension::D2Array(_) => gl::TEXTURE_2D_ARRAY,
+            TextureDimension::Cube => gl::TEXTURE_CUBE_MAP,
+            TextureDimension::CubeArray(_) => gl::TEXTURE_CUBE_MAP_ARRAY,
+            TextureDimension::D3(_) => gl::TEXTURE_3D,
+        }
+    }
+
+    /// Number of 2D images making up the base level, cube faces included.
+    pub fn layers(&self) -> u32 {
+        match *self {
+            TextureDimension::D2 => 1,
+            TextureDimension::D2Array(layers) | TextureDimension::D3(layers) => layers,
+            TextureDimension::Cube => 6,
+            TextureDimension::CubeArray(cubes) => 6 * cubes,
+        }
+    }
+
+    /// Whether the texture has several layers, which image units bind all at once.
+    pub fn is_layered(&self) -> bool {
+        !matches!(self, TextureDimension::D2)
+    }
+
+    /// Number of layers of mip `level`, only shrinking for volumes.
+    pub fn layers_at(&self, level: u32) -> u32 {
+        match *self {
+            TextureDimension::D3(depth) => (depth >> level).max(1),
+            _ => self.layers(),
+        }
     }
 }
 
+/// A texture and its storage. 2D textures can have their contents replaced behind shared
+/// references, so the shape is kept in cells.
 pub struct Texture {
     handle: GLHandle,
-    size: glam::UVec2,
-    format: ImageFormat,
+    dimension: TextureDimension,
+    size: Cell<glam::UVec2>,
+    levels: Cell<u32>,
+    format: Cell<ImageFormat>,
+    source: Option<TextureSource>,
 }
 
-fn create_handle() -> GLHandle {
+fn create_handle(target: GLenum) -> GLHandle {
     let mut handle = 0;
     unsafe {
-        gl::CreateTextures(gl::TEXTURE_2D, 1, &mut handle);
+        gl::CreateTextures(target, 1, &mut handle);
     }
 
     GLHandle::new(handle)
 }
 
 impl Texture {
+    /// Upload `data` as a 2D texture, see `from_layers`. The texture keeps the source of the
+    /// data, so that it can be reloaded.
     pub fn new(data: &TextureData) -> Self {
+        let mut texture = Self::from_layers(TextureDimension::D2, std::slice::from_ref(data));
+        texture.source = data.source.clone();
+        texture
+    }
+
+    /// Uninitialized storage with `levels` mip levels.
+    pub fn with_dimension(
+        size: glam::UVec2,
+        dimension: TextureDimension,
+        levels: u32,
+        format: ImageFormat,
+    ) -> Self {
+        if matches!(
+            dimension,
+            TextureDimension::Cube | TextureDimension::CubeArray(_)
+        ) {
+            assert_eq!(size.x, size.y, "Cubemap faces must be square");
+        }
+        assert!(
+            levels <= Texture::mip_levels_for(size, dimension),
+            "Too many mip levels for a {}x{} {dimension:?} texture",
+            size.x,
+            size.y
+        );
+
         let new = Self {
-            handle: create_handle(),
-            size: data.size,
-            format: data.format,
+            handle: create_handle(dimension.target()),
+            dimension,
+            size: Cell::new(size),
+            levels: Cell::new(levels),
+            format: Cell::new(format),
+            source: None,
         };
 
         unsafe {
-            let gl_format: ImageFormatGL = data.format.to_gl();
-
-            gl::TextureStorage2D(
-                new.handle.get(),
-                Texture::mip_levels(new.size) as i32,
-                gl_format.internal_format,
-                new.size.x as i32,
-                new.size.y as i32,
-            );
-            gl::TextureSubImage2D(
-                new.handle.get(),
-                0,
-                0,
-                0,
-                new.size.x as i32,
-                new.size.y as i32,
-                gl_format.format,
-                gl_format.component_type,
-                data.data.as_ptr() as *const std::ffi::c_void,
-            );
-            gl::GenerateTextureMipmap(new.handle.get());
+            let gl_format: ImageFormatGL = format.to_gl();
+
+            match dimension {
+                TextureDimension::D2 | TextureDimension::Cube => {
+                    gl::TextureStorage2D(
+                        new.handle.get(),
+                        levels as i32,
+                        gl_format.internal_format,
+                        size.x as i32,
+                        size.y as i32,
+                    );
+                }
+                _ => {
+                    gl::TextureStorage3D(
+                        new.handle.get(),
+                        levels as i32,
+                        gl_format.internal_format,
+                        size.x as i32,
+                        size.y as i32,
+                        dimension.layers() as i32,
+                    );
+                }
+            }
         };
 
         new
     }
 
+    /// Upload one `TextureData` per layer: array layers, cube faces or volume slices.
+    ///
+    /// Layers must agree in size, format and mip count. Pre-built mip chains are uploaded as
+    /// is, otherwise mipmaps of uncompressed data are generated. Volumes always generate theirs,
+    /// as their slices shrink along with the levels. Compressed formats the driver lacks are
+    /// decompressed first.
+    pub fn from_layers(dimension: TextureDimension, layers: &[TextureData]) -> Self {
+        assert_eq!(
+            layers.len() as u32,
+            dimension.layers(),
+            "Layer count does not match {dimension:?}"
+        );
+        let first = &layers[0];
+        for layer in layers {
+            assert!(
+                layer.size == first.size
+                    && layer.format == first.format
+                    && layer.levels.len() == first.levels.len(),
+                "Layers differ in size, format or mip count"
+            );
+
+            for (level, pixels) in layer.levels.iter().enumerate() {
+                let size = mip_size(layer.size, level as u32);
+                assert_eq!(
+                    pixels.len(),
+                    layer.format.data_size(size),
+                    "Level {level} holds {} bytes, a {}x{} {:?} image takes {}",
+                    pixels.len(),
+                    size.x,
+                    size.y,
+                    layer.format,
+                    layer.format.data_size(size)
+                );
+            }
+        }
+
+        if first.format.is_compressed() && !first.format.is_supported(dimension) {
+            let layers: Vec<_> = layers.iter().map(TextureData::decompress).collect();
+            return Self::from_layers(dimension, &layers);
+        }
+
+        let is_volume = matches!(dimension, TextureDimension::D3(_));
+        assert!(
+            !is_volume || first.levels.len() == 1,
+            "Volume textures cannot take pre-built mip chains"
+        );
+
+        let (levels, generate_mips) = Self::storage_levels(first, dimension);
+
+        let new = Self::with_dimension(first.size, dimension, levels, first.format);
+        for (layer, data) in layers.iter().enumerate() {
+            for (level, pixels) in data.levels.iter().enumerate() {
+                new.upload(level as u32, layer as u32, pixels);
+            }
+        }
+
+        if generate_mips {
+            new.generate_mipmaps();
+        }
+
+        new
+    }
+
     pub fn new_from_format(size: &glam::UVec2, format: &ImageFormat) -> Self {
-        let new = Self {
-            handle: create_handle(),
-            size: *size,
-            format: *format,
-        };
+        Self::with_dimension(*size, TextureDimension::D2, 1, *format)
+    }
+
+    /// Uninitialized array of `layers` 2D images with a single mip level.
+    pub fn new_array(size: &glam::UVec2, layers: u32, format: &ImageFormat) -> Self {
+        Self::with_dimension(*size, TextureDimension::D2Array(layers), 1, *format)
+    }
+
+    /// Uninitialized cubemap with `size` faces and a single mip level.
+    pub fn new_cube(size: u32, format: &ImageFormat) -> Self {
+        Self::with_dimension(glam::UVec2::splat(size), TextureDimension::Cube, 1, *format)
+    }
+
+    /// Uninitialized volume with a single mip level.
+    pub fn new_3d(size: glam::UVec3, format: &ImageFormat) -> Self {
+        Self::with_dimension(size.truncate(), TextureDimension::D3(size.z), 1, *format)
+    }
+
+    /// Replace the pixels of one layer of mip `level`, tightly packed in the texture's format.
+    pub fn upload(&self, level: u32, layer: u32, pixels: &[u8]) {
+        assert!(level < self.levels(), "Texture has no mip level {level}");
+        assert!(
+            layer < self.dimension.layers_at(level),
+            "Texture has no layer {layer} at level {level}"
+        );
+
+        let size = mip_size(self.size(), level);
+        assert_eq!(
+            pixels.len(),
+            self.format().data_size(size),
+            "Pixel data does not match a {}x{} {:?} image",
+            size.x,
+            size.y,
+            self.format()
+        );
+
+        let gl_format = self.format().to_gl();
+        let pixels_ptr = pixels.as_ptr() as *const std::ffi::c_void;
 
         unsafe {
-            let gl_format: ImageFormatGL = format.to_gl();
-            gl::TextureStorage2D(
-                new.handle.get(),
-                1,
-                gl_format.internal_format,
-                new.size.x as i32,
-                new.size.y as i32,
-            );
+            // Rows of one to three byte pixels are not padded to 4 bytes.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+            match (self.dimension, self.format().is_compressed()) {
+                (TextureDimension::D2, false) => gl::TextureSubImage2D(
+                    self.handle.get(),
+                    level as i32,
+                    0,
+                    0,
+                    size.x as i32,
+                    size.y as i32,
+                    gl_format.format,
+                    gl_format.component_type,
+                    pixels_ptr,
+                ),
+                (TextureDimension::D2, true) => gl::CompressedTextureSubImage2D(
+                    self.handle.get(),
+                    level as i32,
+                    0,
+                    0,
+                    size.x as i32,
+                    size.y as i32,
+                    gl_format.internal_format,
+                    pixels.len() as i32,
+                    pixels_ptr,
+                ),
+                // Cube faces are addressed as layers through the direct state access entry points.
+                (_, false) => gl::TextureSubImage3D(
+                    self.handle.get(),
+                    level as i32,
+                    0,
+                    0,
+                    layer as i32,
+                    size.x as i32,
+                    size.y as i32,
+                    1,
+                    gl_format.format,
+                    gl_format.component_type,
+                    pixels_ptr,
+                ),
+                (_, true) => gl::CompressedTextureSubImage3D(
+                    self.handle.get(),
+                    level as i32,
+                    0,
+                    0,
+                    layer as i32,
+                    size.x as i32,
+                    size.y as i32,
+                    1,
+                    gl_format.internal_format,
+                    pixels.len() as i32,
+                    pixels_ptr,
+                ),
+            }
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+    }
+
+    /// Download mip `level` of a 2D texture, see `read_layer`.
+    pub fn read_pixels(&self, level: u32, flip: bool) -> TextureData {
+        assert!(
+            !self.dimension.is_layered(),
+            "Read a layer of {:?} textures instead",
+            self.dimension
+        );
+        self.read_layer(level, 0, flip)
+    }
+
+    /// Download one layer of mip `level`. Rows come in upload order, so rendered images start
+    /// with their bottom row. `flip` reverses them, turning rendered images top-down as
+    /// `TextureData::save_png` expects.
+    pub fn read_layer(&self, level: u32, layer: u32, flip: bool) -> TextureData {
+        assert!(level < self.levels(), "Texture has no mip level {level}");
+        assert!(
+            layer < self.dimension.layers_at(level),
+            "Texture has no layer {layer} at level {level}"
+        );
+
+        let size = mip_size(self.size(), level);
+        let mut pixels = vec![0u8; self.format().data_size(size)];
+        let gl_format = self.format().to_gl();
+
+        unsafe {
+            // Rows of RGB8 images are not padded to 4 bytes.
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+            if self.format().is_compressed() {
+                gl::GetCompressedTextureSubImage(
+                    self.handle.get(),
+                    level as i32,
+                    0,
+                    0,
+                    layer as i32,
+                    size.x as i32,
+                    size.y as i32,
+                    1,
+                    pixels.len() as i32,
+                    pixels.as_mut_ptr() as *mut std::ffi::c_void,
+                );
+            } else {
+                gl::GetTextureSubImage(
+                    self.handle.get(),
+                    level as i32,
+                    0,
+                    0,
+                    layer as i32,
+                    size.x as i32,
+                    size.y as i32,
+                    1,
+                    gl_format.format,
+                    gl_format.component_type,
+                    pixels.len() as i32,
+                    pixels.as_mut_ptr() as *mut std::ffi::c_void,
+                );
+            }
+
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+        }
+
+        let mut data = TextureData::new(pixels, size, self.format());
+        if flip {
+            data.flip_vertically();
+        }
+        data
+    }
+
+    /// Replace the contents of a 2D texture with `data`, keeping its identity so that every
+    /// holder of it sees the change.
+    ///
+    /// Data of the same size, format and mip count is uploaded into the existing storage.
+    /// Otherwise the storage is recreated, and framebuffers or image bindings using the
+    /// texture must be set up again.
+    pub fn replace(&self, data: &TextureData) {
+        assert_eq!(
+            self.dimension,
+            TextureDimension::D2,
+            "Only 2D textures can be replaced"
+        );
+
+        // Decode formats the driver lacks up front, like `from_layers`, to compare shapes.
+        let decompressed;
+        let data = if data.format.is_compressed() && !data.format.is_supported(self.dimension) {
+            decompressed = data.decompress();
+            &decompressed
+        } else {
+            data
         };
 
-        new
+        let (levels, generate_mips) = Self::storage_levels(data, self.dimension);
+        if (data.size, data.format, levels) != (self.size(), self.format(), self.levels()) {
+            let fresh = Texture::from_layers(self.dimension, std::slice::from_ref(data));
+            // The previous storage goes away with `fresh`.
+            self.handle.swap(&fresh.handle);
+            self.size.set(fresh.size());
+            self.format.set(fresh.format());
+            self.levels.set(fresh.levels());
+            return;
+        }
+
+        for (level, pixels) in data.levels.iter().enumerate() {
+            self.upload(level as u32, 0, pixels);
+        }
+        if generate_mips {
+            self.generate_mipmaps();
+        }
+    }
+
+    /// File the texture was loaded from, if any.
+    pub fn source(&self) -> Option<&TextureSource> {
+        self.source.as_ref()
+    }
+
+    /// Load the source file again and replace the contents with it, see `replace`.
+    pub fn reload(&self) -> Result<(), TextureError> {
+        let source = self.source.as_ref().ok_or_else(|| {
+            TextureError::Unsupported("reload of a texture without a source file".to_owned())
+        })?;
+        self.replace(&TextureData::from_file(&source.path, source.color_space)?);
+        Ok(())
+    }
+
+    /// Rebuild every mip level from the base one.
+    pub fn generate_mipmaps(&self) {
+        assert!(
+            self.format().supports_mipmap_generation(),
+            "Cannot generate mipmaps of {:?}",
+            self.format()
+        );
+
+        unsafe {
+            gl::GenerateTextureMipmap(self.handle.get());
+        }
     }
 
     pub fn bind(&self, index: GLuint) {
@@ -166,22 +894,62 @@ impl Texture {
         }
     }
 
+    /// Bind the base level to an image unit, all layers at once when there are several.
     pub fn bind_as_image(&self, index: GLuint, access: AccessType) {
+        self.bind_level_as_image(index, 0, None, access);
+    }
+
+    /// Bind mip `level` to an image unit, either a single `layer` or every layer.
+    pub fn bind_level_as_image(
+        &self,
+        index: GLuint,
+        level: u32,
+        layer: Option<u32>,
+        access: AccessType,
+    ) {
+        assert!(level < self.levels(), "Texture has no mip level {level}");
+        assert!(
+            self.format().supports_image_load_store(),
+            "{:?} textures cannot be bound as images",
+            self.format()
+        );
+
         unsafe {
             gl::BindImageTexture(
                 index,
                 self.handle.get(),
-                0,
-                false as gl::types::GLboolean,
-                0,
+                level as i32,
+                (layer.is_none() && self.dimension.is_layered()) as gl::types::GLboolean,
+                layer.unwrap_or(0) as i32,
                 access.into(),
-                self.format.to_gl().internal_format,
+                self.format().to_gl().internal_format,
             );
         }
     }
 
-    pub fn size(&self) -> &glam::UVec2 {
-        &self.size
+    pub fn handle(&self) -> &GLHandle {
+        &self.handle
+    }
+
+    pub fn size(&self) -> glam::UVec2 {
+        self.size.get()
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format.get()
+    }
+
+    pub fn dimension(&self) -> TextureDimension {
+        self.dimension
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.dimension.layers()
+    }
+
+    /// Number of mip levels of the storage.
+    pub fn levels(&self) -> u32 {
+        self.levels.get()
     }
 
     /// Return number of mip levels needed.
@@ -189,6 +957,24 @@ impl Texture {
         let side = size.max_element() as f32;
         1 + side.log2().floor() as u32
     }
+
+    /// Mip levels to allocate for `data`, and whether to generate them rather than upload a
+    /// single level.
+    fn storage_levels(data: &TextureData, dimension: TextureDimension) -> (u32, bool) {
+        if data.levels.len() == 1 && data.format.supports_mipmap_generation() {
+            (Self::mip_levels_for(data.size, dimension), true)
+        } else {
+            (data.levels.len() as u32, false)
+        }
+    }
+
+    /// Mip levels of a full chain, volumes also shrinking in depth.
+    pub fn mip_levels_for(size: glam::UVec2, dimension: TextureDimension) -> u32 {
+        match dimension {
+            TextureDimension::D3(depth) => Texture::mip_levels(size.max(glam::UVec2::splat(depth))),
+            _ => Texture::mip_levels(size),
+        }
+    }
 }
 
 impl Drop for Texture {
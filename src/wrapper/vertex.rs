@@ -0,0 +1,396 @@
+use std::mem::{offset_of, size_of};
+
+use gl::types::GLenum;
+
+use super::{buffer::GLBuffer, handle::BufferUsage};
+
+/// Attribute locations used by the built-in vertex formats.
+pub mod location {
+    pub const POSITION: u32 = 0;
+    pub const NORMAL: u32 = 1;
+    pub const COLOR: u32 = 2;
+    pub const UV: u32 = 3;
+    pub const TANGENT: u32 = 4;
+    pub const JOINTS: u32 = 5;
+    pub const WEIGHTS: u32 = 6;
+
+    /// First of the four columns of the per-instance transform.
+    pub const INSTANCE_TRANSFORM: u32 = 8;
+    pub const INSTANCE_COLOR: u32 = 12;
+    pub const INSTANCE_CUSTOM: u32 = 13;
+}
+
+/// Highest number of attribute locations a vertex format may use.
+pub const MAX_VERTEX_ATTRIBUTES: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComponentType {
+    F32,
+    U8,
+    U16,
+    U32,
+    I8,
+    I16,
+    I32,
+}
+
+impl ComponentType {
+    pub fn size(&self) -> usize {
+        match self {
+            ComponentType::U8 | ComponentType::I8 => 1,
+            ComponentType::U16 | ComponentType::I16 => 2,
+            ComponentType::F32 | ComponentType::U32 | ComponentType::I32 => 4,
+        }
+    }
+}
+
+impl From<ComponentType> for GLenum {
+    fn from(value: ComponentType) -> Self {
+        match value {
+            ComponentType::F32 => gl::FLOAT,
+            ComponentType::U8 => gl::UNSIGNED_BYTE,
+            ComponentType::U16 => gl::UNSIGNED_SHORT,
+            ComponentType::U32 => gl::UNSIGNED_INT,
+            ComponentType::I8 => gl::BYTE,
+            ComponentType::I16 => gl::SHORT,
+            ComponentType::I32 => gl::INT,
+        }
+    }
+}
+
+/// How the shader sees an attribute's components.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeKind {
+    /// Converted to float as-is.
+    Float,
+    /// Integer components mapped to [0, 1] (or [-1, 1] when signed).
+    Normalized,
+    /// Kept as integers (`uvec`/`ivec` in GLSL).
+    Integer,
+}
+
+/// Description of a single attribute within a vertex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub components: u32,
+    pub component_type: ComponentType,
+    pub kind: AttributeKind,
+    /// Byte offset from the start of the vertex.
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    pub const fn float(location: u32, components: u32, offset: usize) -> Self {
+        Self {
+            location,
+            components,
+            component_type: ComponentType::F32,
+            kind: AttributeKind::Float,
+            offset,
+        }
+    }
+
+    pub const fn integer(
+        location: u32,
+        components: u32,
+        component_type: ComponentType,
+        offset: usize,
+    ) -> Self {
+        Self {
+            location,
+            components,
+            component_type,
+            kind: AttributeKind::Integer,
+            offset,
+        }
+    }
+
+    pub fn byte_size(&self) -> usize {
+        self.components as usize * self.component_type.size()
+    }
+
+    /// Point the currently bound attribute buffer at this attribute, advancing once per
+    /// `divisor` instances (or once per vertex when 0).
+    pub(crate) fn bind_pointer(&self, stride: usize, offset: usize, divisor: u32) {
+        let offset = offset as *const std::ffi::c_void;
+
+        unsafe {
+            gl::EnableVertexAttribArray(self.location);
+            gl::VertexAttribDivisor(self.location, divisor);
+
+            match self.kind {
+                AttributeKind::Integer => gl::VertexAttribIPointer(
+                    self.location,
+                    self.components as i32,
+                    self.component_type.into(),
+                    stride as i32,
+                    offset,
+                ),
+                AttributeKind::Float | AttributeKind::Normalized => gl::VertexAttribPointer(
+                    self.location,
+                    self.components as i32,
+                    self.component_type.into(),
+                    (self.kind == AttributeKind::Normalized) as gl::types::GLboolean,
+                    stride as i32,
+                    offset,
+                ),
+            }
+        }
+    }
+}
+
+/// A type that can be uploaded as a vertex.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` so that the attribute offsets match the memory layout,
+/// and every attribute in `ATTRIBUTES` must cover bytes of a single field: in bounds of the
+/// vertex, and without padding, whose bytes are uninitialised.
+pub unsafe trait VertexFormat: Sized + Clone {
+    /// Attributes making up the vertex, in any order.
+    const ATTRIBUTES: &'static [VertexAttribute];
+
+    /// Object-space position, used for bounds computation.
+    fn position(&self) -> glam::Vec3;
+}
+
+/// Position, normal and color.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub color: glam::Vec3,
+}
+
+impl Default for Vertex {
+    fn default() -> Self {
+        Self {
+            position: glam::vec3(0., 0., 0.),
+            normal: glam::vec3(0., 1., 0.),
+            color: glam::vec3(1., 1., 1.),
+        }
+    }
+}
+
+unsafe impl VertexFormat for Vertex {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute::float(location::POSITION, 3, offset_of!(Vertex, position)),
+        VertexAttribute::float(location::NORMAL, 3, offset_of!(Vertex, normal)),
+        VertexAttribute::float(location::COLOR, 3, offset_of!(Vertex, color)),
+    ];
+
+    fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+}
+
+/// Position only.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VertexP {
+    pub position: glam::Vec3,
+}
+
+unsafe impl VertexFormat for VertexP {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[VertexAttribute::float(
+        location::POSITION,
+        3,
+        offset_of!(VertexP, position),
+    )];
+
+    fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+}
+
+/// Position and normal.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VertexPN {
+    pub position: glam::Vec3,
+    pub normal: glam::Vec3,
+}
+
+unsafe impl VertexFormat for VertexPN {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute::float(location::POSITION, 3, offset_of!(VertexPN, position)),
+        VertexAttribute::float(location::NORMAL, 3, offset_of!(VertexPN, normal)),
+    ];
+
+    fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+}
+
+/// Position, normal and texture coordinates.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VertexPNUV {
+    pub position: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub uv: glam::Vec2,
+}
+
+unsafe impl VertexFormat for VertexPNUV {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute::float(location::POSITION, 3, offset_of!(VertexPNUV, position)),
+        VertexAttribute::float(location::NORMAL, 3, offset_of!(VertexPNUV, normal)),
+        VertexAttribute::float(location::UV, 2, offset_of!(VertexPNUV, uv)),
+    ];
+
+    fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+}
+
+/// Position, normal, texture coordinates and tangent, for normal mapping.
+///
+/// The tangent's `w` holds the bitangent sign.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VertexPNUVT {
+    pub position: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub uv: glam::Vec2,
+    pub tangent: glam::Vec4,
+}
+
+unsafe impl VertexFormat for VertexPNUVT {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute::float(location::POSITION, 3, offset_of!(VertexPNUVT, position)),
+        VertexAttribute::float(location::NORMAL, 3, offset_of!(VertexPNUVT, normal)),
+        VertexAttribute::float(location::UV, 2, offset_of!(VertexPNUVT, uv)),
+        VertexAttribute::float(location::TANGENT, 4, offset_of!(VertexPNUVT, tangent)),
+    ];
+
+    fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+}
+
+/// Normal-mapped vertex influenced by up to four joints.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VertexSkinned {
+    pub position: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub uv: glam::Vec2,
+    pub tangent: glam::Vec4,
+    pub joints: [u16; 4],
+    pub weights: glam::Vec4,
+}
+
+unsafe impl VertexFormat for VertexSkinned {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute::float(location::POSITION, 3, offset_of!(VertexSkinned, position)),
+        VertexAttribute::float(location::NORMAL, 3, offset_of!(VertexSkinned, normal)),
+        VertexAttribute::float(location::UV, 2, offset_of!(VertexSkinned, uv)),
+        VertexAttribute::float(location::TANGENT, 4, offset_of!(VertexSkinned, tangent)),
+        VertexAttribute::integer(
+            location::JOINTS,
+            4,
+            ComponentType::U16,
+            offset_of!(VertexSkinned, joints),
+        ),
+        VertexAttribute::float(location::WEIGHTS, 4, offset_of!(VertexSkinned, weights)),
+    ];
+
+    fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+}
+
+/// Per-instance attributes used by instanced draws.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstanceData {
+    pub transform: glam::Mat4,
+    pub color: glam::Vec4,
+    /// Free for shaders to interpret.
+    pub custom: glam::Vec4,
+}
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        Self {
+            transform: glam::Mat4::IDENTITY,
+            color: glam::Vec4::ONE,
+            custom: glam::Vec4::ZERO,
+        }
+    }
+}
+
+impl InstanceData {
+    pub const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute::float(
+            location::INSTANCE_TRANSFORM,
+            4,
+            offset_of!(InstanceData, transform),
+        ),
+        VertexAttribute::float(
+            location::INSTANCE_TRANSFORM + 1,
+            4,
+            offset_of!(InstanceData, transform) + size_of::<glam::Vec4>(),
+        ),
+        VertexAttribute::float(
+            location::INSTANCE_TRANSFORM + 2,
+            4,
+            offset_of!(InstanceData, transform) + 2 * size_of::<glam::Vec4>(),
+        ),
+        VertexAttribute::float(
+            location::INSTANCE_TRANSFORM + 3,
+            4,
+            offset_of!(InstanceData, transform) + 3 * size_of::<glam::Vec4>(),
+        ),
+        VertexAttribute::float(location::INSTANCE_COLOR, 4, offset_of!(InstanceData, color)),
+        VertexAttribute::float(
+            location::INSTANCE_CUSTOM,
+            4,
+            offset_of!(InstanceData, custom),
+        ),
+    ];
+
+    /// Bind `buffer` as the per-instance attribute source. Must be called after the mesh's
+    /// vertex streams are bound, as those disable every location they do not use.
+    pub(crate) fn bind_buffer(buffer: &GLBuffer<InstanceData>) {
+        buffer.bind(BufferUsage::Attribute);
+        for attribute in Self::ATTRIBUTES {
+            attribute.bind_pointer(size_of::<InstanceData>(), attribute.offset, 1);
+        }
+    }
+}
+
+/// Copy the bytes of `attribute` within `vertex` into `target`, without reading the padding
+/// around it.
+pub(crate) fn copy_attribute<V: VertexFormat>(
+    vertex: &V,
+    attribute: &VertexAttribute,
+    target: &mut [u8],
+) {
+    assert_eq!(target.len(), attribute.byte_size());
+
+    // SAFETY: `VertexFormat` guarantees the attribute covers initialised bytes of `vertex`.
+    unsafe {
+        let source = (vertex as *const V as *const u8).add(attribute.offset);
+        std::ptr::copy_nonoverlapping(source, target.as_mut_ptr(), target.len());
+    }
+}
+
+/// Split interleaved vertices into one tightly packed byte stream per attribute.
+pub(crate) fn deinterleave<V: VertexFormat>(vertices: &[V]) -> Vec<Vec<u8>> {
+    V::ATTRIBUTES
+        .iter()
+        .map(|attribute| {
+            let mut bytes = vec![0; vertices.len() * attribute.byte_size()];
+            for (vertex, target) in vertices
+                .iter()
+                .zip(bytes.chunks_exact_mut(attribute.byte_size()))
+            {
+                copy_attribute(vertex, attribute, target);
+            }
+            bytes
+        })
+        .collect()
+}
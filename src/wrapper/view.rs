@@ -0,0 +1,209 @@
+use std::rc::Rc;
+
+use gl::types::GLuint;
+
+use crate::dogl;
+
+use super::{
+    camera::{Camera, Viewport},
+    framebuffer::Framebuffer,
+    texture::{ImageFormat, Texture, TextureData},
+};
+
+/// Textures rendered to through a framebuffer object created once for them.
+pub struct TextureTarget {
+    framebuffer: Framebuffer,
+    color: Rc<Texture>,
+    depth: Option<Rc<Texture>>,
+}
+
+impl TextureTarget {
+    /// Target drawing into `color`, depth testing against `depth` when given.
+    pub fn new(color: Rc<Texture>, depth: Option<Rc<Texture>>) -> Self {
+        // Views clear the target themselves.
+        let mut builder = Framebuffer::builder().color(color.clone(), None);
+        if let Some(depth) = &depth {
+            builder = builder.depth(depth.clone(), None);
+        }
+        let framebuffer = builder.build().unwrap_or_else(|err| panic!("{err}"));
+
+        Self {
+            framebuffer,
+            color,
+            depth,
+        }
+    }
+
+    pub fn color(&self) -> &Rc<Texture> {
+        &self.color
+    }
+
+    pub fn depth(&self) -> Option<&Rc<Texture>> {
+        self.depth.as_ref()
+    }
+
+    pub fn size(&self) -> glam::UVec2 {
+        self.color.size()
+    }
+}
+
+/// Where a view's image ends up.
+pub enum ViewTarget {
+    /// The window, whose framebuffer size is needed to place top-left based viewports.
+    Default(glam::UVec2),
+    Texture(TextureTarget),
+    /// A framebuffer cleared with its attachments' own values, such as a multisampled one
+    /// resolved afterwards.
+    Framebuffer(Rc<Framebuffer>),
+}
+
+impl ViewTarget {
+    pub fn size(&self) -> glam::UVec2 {
+        match self {
+            ViewTarget::Default(size) => *size,
+            ViewTarget::Texture(target) => target.size(),
+            ViewTarget::Framebuffer(framebuffer) => framebuffer.size(),
+        }
+    }
+
+    fn framebuffer(&self) -> GLuint {
+        match self {
+            ViewTarget::Default(_) => 0,
+            ViewTarget::Texture(target) => target.framebuffer.handle().get(),
+            ViewTarget::Framebuffer(framebuffer) => framebuffer.handle().get(),
+        }
+    }
+
+    /// Read back the RGBA8 pixels of `viewport`, rows going from top to bottom.
+    ///
+    /// The window's pixels are read from the back buffer, so before swapping. They are
+    /// tagged sRGB as they are encoded on write, as are those of sRGB color textures.
+    pub fn read_pixels(&self, viewport: &Viewport) -> TextureData {
+        let Viewport {
+            x,
+            y,
+            width,
+            height,
+        } = *viewport;
+        // GL places the origin at the bottom-left corner.
+        let y = self.size().y as i32 - y - height as i32;
+
+        let is_srgb = match self {
+            ViewTarget::Default(_) => true,
+            ViewTarget::Texture(target) => target.color.format().is_srgb(),
+            ViewTarget::Framebuffer(framebuffer) => framebuffer
+                .color(0)
+                .is_some_and(|color| color.format().is_srgb()),
+        };
+        let format = if is_srgb {
+            ImageFormat::Rgba8SRgb
+        } else {
+            ImageFormat::Rgba8Unorm
+        };
+
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        unsafe {
+            dogl!(gl::BindFramebuffer(
+                gl::READ_FRAMEBUFFER,
+                self.framebuffer()
+            ));
+            dogl!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1));
+            dogl!(gl::ReadnPixels(
+                x,
+                y,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.len() as i32,
+                pixels.as_mut_ptr() as *mut std::ffi::c_void
+            ));
+            dogl!(gl::PixelStorei(gl::PACK_ALIGNMENT, 4));
+            dogl!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0));
+        }
+
+        let mut data = TextureData::new(pixels, glam::uvec2(width, height), format);
+        data.flip_vertically();
+        data
+    }
+}
+
+/// A camera rendering into a rectangle of a target.
+///
+/// Rendering several views per frame gives split screen, picture-in-picture or mirror effects.
+pub struct View {
+    pub camera: Camera,
+    pub viewport: Viewport,
+    pub target: ViewTarget,
+    /// Color the viewport is cleared to before rendering, or `None` to keep the previous
+    /// contents. Depth is always cleared. Framebuffer targets use their own clear values
+    /// instead.
+    pub clear_color: Option<glam::Vec4>,
+}
+
+impl View {
+    pub fn new(camera: Camera, viewport: Viewport, target: ViewTarget) -> Self {
+        Self {
+            camera,
+            viewport,
+            target,
+            clear_color: None,
+        }
+    }
+
+    /// View covering the whole of `target`.
+    pub fn full(camera: Camera, target: ViewTarget) -> Self {
+        let size = target.size();
+        Self::new(camera, Viewport::full(size.x, size.y), target)
+    }
+
+    /// Match the camera's aspect ratio to the viewport's, keeping it as is for empty
+    /// viewports.
+    pub fn fit_camera(&mut self) {
+        if !self.viewport.is_empty() {
+            self.camera.set_ratio(self.viewport.ratio());
+        }
+    }
+
+    /// Bind the target and restrict drawing to the viewport, clearing it.
+    pub(crate) fn begin(&self) {
+        let Viewport {
+            x,
+            y,
+            width,
+            height,
+        } = self.viewport;
+        // GL places the origin at the bottom-left corner.
+        let y = self.target.size().y as i32 - y - height as i32;
+
+        unsafe {
+            dogl!(gl::BindFramebuffer(
+                gl::DRAW_FRAMEBUFFER,
+                self.target.framebuffer()
+            ));
+            dogl!(gl::Viewport(x, y, width as i32, height as i32));
+            dogl!(gl::Scissor(x, y, width as i32, height as i32));
+            dogl!(gl::Enable(gl::SCISSOR_TEST));
+
+            if let ViewTarget::Framebuffer(framebuffer) = &self.target {
+                framebuffer.clear();
+                return;
+            }
+
+            let mut mask = gl::DEPTH_BUFFER_BIT;
+            if let Some(color) = self.clear_color {
+                gl::ClearColor(color.x, color.y, color.z, color.w);
+                mask |= gl::COLOR_BUFFER_BIT;
+            }
+            dogl!(gl::Clear(mask));
+        }
+    }
+
+    /// Restore the default framebuffer and drop the scissor rectangle.
+    pub(crate) fn end(&self) {
+        unsafe {
+            dogl!(gl::Disable(gl::SCISSOR_TEST));
+            dogl!(gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0));
+        }
+    }
+}
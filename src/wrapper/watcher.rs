@@ -0,0 +1,126 @@
+//! Reloading of textures whose source files change on disk.
+
+use std::{
+    path::PathBuf,
+    rc::{Rc, Weak},
+    time::{Duration, Instant, SystemTime},
+};
+
+use super::{
+    image::{TextureError, TextureSource},
+    texture::{Texture, TextureData},
+};
+
+/// Textures loaded from one file the same way.
+struct WatchedSource {
+    source: TextureSource,
+    /// Modification time of the file when its oldest texture was loaded.
+    modified: Option<SystemTime>,
+    textures: Vec<Weak<Texture>>,
+}
+
+fn modified(source: &TextureSource) -> Option<SystemTime> {
+    std::fs::metadata(&source.path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Polls the modification time of the files textures were loaded from, and replaces the
+/// contents of those that changed in place, so that every material holding them picks the
+/// change up.
+///
+/// Textures are held weakly and forgotten once dropped.
+pub struct TextureWatcher {
+    sources: Vec<WatchedSource>,
+    interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+impl TextureWatcher {
+    /// Watcher checking files at most once per `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            sources: Vec::new(),
+            interval,
+            last_poll: None,
+        }
+    }
+
+    /// Start watching the source file of `texture`, which must have been created from
+    /// `TextureData::from_file`. Changes made since it was loaded are picked up by the next
+    /// poll.
+    pub fn watch(&mut self, texture: &Rc<Texture>) {
+        let source = texture
+            .source()
+            .expect("Only textures loaded from a file can be watched");
+
+        let weak = Rc::downgrade(texture);
+        match self.sources.iter_mut().find(|watched| {
+            watched.source.path == source.path && watched.source.color_space == source.color_space
+        }) {
+            Some(watched) => {
+                if !watched.textures.iter().any(|other| other.ptr_eq(&weak)) {
+                    watched.textures.push(weak);
+                    // Textures loaded before a change are reloaded along with the others.
+                    watched.modified = watched.modified.min(source.modified);
+                }
+            }
+            None => self.sources.push(WatchedSource {
+                source: source.clone(),
+                modified: source.modified,
+                textures: vec![weak],
+            }),
+        }
+    }
+
+    /// Number of files being watched.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Reload the textures of files modified since the last check, once the interval has
+    /// passed. Returns each changed file with the outcome of its reload.
+    ///
+    /// Files that fail to load, such as ones still being written, leave their textures as
+    /// they were and are tried again on their next change.
+    pub fn poll(&mut self) -> Vec<(PathBuf, Result<(), TextureError>)> {
+        let now = Instant::now();
+        if self
+            .last_poll
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return Vec::new();
+        }
+        self.last_poll = Some(now);
+
+        self.sources.retain_mut(|watched| {
+            watched
+                .textures
+                .retain(|texture| texture.strong_count() > 0);
+            !watched.textures.is_empty()
+        });
+
+        let mut reloads = Vec::new();
+        for watched in &mut self.sources {
+            let modified = modified(&watched.source);
+            // Files missing for a moment while being saved keep their last time.
+            if modified.is_none() || modified == watched.modified {
+                continue;
+            }
+            watched.modified = modified;
+
+            let result = TextureData::from_file(&watched.source.path, watched.source.color_space)
+                .map(|data| {
+                    for texture in watched.textures.iter().filter_map(Weak::upgrade) {
+                        texture.replace(&data);
+                    }
+                });
+            reloads.push((watched.source.path.clone(), result));
+        }
+        reloads
+    }
+}